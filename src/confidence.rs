@@ -0,0 +1,86 @@
+//! Confidence intervals for simulated estimates.
+
+use serde::Serialize;
+
+/// Confidence level of every reported interval.
+pub const CONFIDENCE: f64 = 0.95;
+
+/// Standard normal quantile for [`CONFIDENCE`].
+pub const Z: f64 = 1.959_963_984_540_054;
+
+/// Below this many successes the estimates are too noisy to rely on.
+pub const MIN_RELIABLE_COUNT: u64 = 10;
+
+/// Range a true value lies in with [`CONFIDENCE`] confidence.
+///
+/// Bounds can be infinite, e.g. the average flames needed for a target that was never hit.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Interval {
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl Interval {
+    /// Maps both bounds through a monotonically increasing function.
+    pub fn map<F: Fn(f64) -> f64>(self, f: F) -> Interval {
+        Interval {
+            lower: f(self.lower),
+            upper: f(self.upper),
+        }
+    }
+
+    /// Maps both bounds through a monotonically decreasing function, swapping them.
+    pub fn map_rev<F: Fn(f64) -> f64>(self, f: F) -> Interval {
+        Interval {
+            lower: f(self.upper),
+            upper: f(self.lower),
+        }
+    }
+}
+
+/// Wilson score interval of a success probability.
+pub fn wilson(successes: u64, trials: u64) -> Interval {
+    if trials == 0 {
+        return Interval {
+            lower: 0.0,
+            upper: 1.0,
+        };
+    }
+    let n = trials as f64;
+    let p = successes as f64 / n;
+    let z2 = Z * Z;
+    let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+    let half_width = Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+    // the bounds are exact at the edges, where rounding would leave them slightly off
+    Interval {
+        lower: if successes == 0 {
+            0.0
+        } else {
+            (center - half_width).max(0.0)
+        },
+        upper: if successes == trials {
+            1.0
+        } else {
+            (center + half_width).min(1.0)
+        },
+    }
+}
+
+/// Normal interval of a probability estimated as the mean of `trials` weighted indicators,
+/// from the sum and sum of squares of the weights.
+pub fn weighted(sum: f64, sum_sq: f64, trials: u64) -> Interval {
+    if trials < 2 {
+        return Interval {
+            lower: 0.0,
+            upper: 1.0,
+        };
+    }
+    let n = trials as f64;
+    let mean = sum / n;
+    let variance = ((sum_sq - n * mean * mean) / (n - 1.0)).max(0.0);
+    let half_width = Z * (variance / n).sqrt();
+    Interval {
+        lower: (mean - half_width).max(0.0),
+        upper: (mean + half_width).min(1.0),
+    }
+}
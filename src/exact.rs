@@ -0,0 +1,616 @@
+//! Exact flame-score distribution, computed by enumerating line combinations and tiers.
+//!
+//! A flame picks `k` distinct lines uniformly from the option table and rolls an independent
+//! tier for each, and every score formula is a weighted sum of stat totals. The score is
+//! therefore a sum of independent per-line contributions over a uniformly chosen subset, which
+//! can be convolved exactly instead of sampled.
+
+use crate::flame::{add_line, FLAME_STATS};
+use crate::simulator::{FlameSimulator, NOBOSS_LINES, NOBOSS_LINE_WEIGHTS};
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Scores are bucketed to this resolution so equal scores reached through different
+/// float operations land in the same bucket.
+const SCORE_SCALE: f64 = 10_000.0;
+
+/// Combined odds of the lowest scores below which [`ScoreDistribution::best_of`] merges them
+/// into the next score, so its summary doesn't start at scores it practically never reaches.
+const NEGLIGIBLE_MASS: f64 = 1e-12;
+
+pub(crate) fn to_key(score: f64) -> i64 {
+    (score * SCORE_SCALE).round() as i64
+}
+
+pub(crate) fn from_key(key: i64) -> f64 {
+    key as f64 / SCORE_SCALE
+}
+
+/// Probability mass function of the flame score, exact or estimated from simulated flames.
+#[derive(Debug, Clone)]
+pub struct ScoreDistribution {
+    /// `(score, probability)` pairs in ascending score order.
+    pub pmf: Vec<(f64, f64)>,
+}
+
+/// Range of scores in a histogram, with the odds of a flame scoring in it.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Bin {
+    /// Lowest score in the bin.
+    pub lower: f64,
+    /// Score the bin ends before, or the highest score for the last bin.
+    pub upper: f64,
+    pub probability: f64,
+}
+
+/// How to flame an item on, from [`ScoreDistribution::stop_rule`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct StopRule {
+    /// Scores to stop flaming at by flames left: with `n` flames left, stop once the item
+    /// scores at least `thresholds[n - 1]`. Flames that keep the old result have a single
+    /// threshold, whatever is left.
+    pub thresholds: Vec<f64>,
+    /// Whether the item is below the threshold for the whole budget.
+    pub keep_flaming: bool,
+    /// Expected final score when following the rule.
+    pub expected_score: f64,
+    /// Expected flames used when following the rule.
+    pub expected_flames: f64,
+}
+
+impl ScoreDistribution {
+    /// Probability of a single flame scoring at least `keep`.
+    pub fn probability_at_least(&self, keep: f64) -> f64 {
+        let keep = to_key(keep);
+        self.pmf
+            .iter()
+            .filter(|(score, _)| to_key(*score) >= keep)
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    /// Probability of a single flame scoring higher than `score`.
+    pub fn probability_above(&self, score: f64) -> f64 {
+        let score = to_key(score);
+        self.pmf
+            .iter()
+            .filter(|(other, _)| to_key(*other) > score)
+            .map(|(_, p)| p)
+            .sum()
+    }
+
+    /// Expected amount of flames needed to score at least `keep`, or 0 if it's impossible.
+    pub fn average_flames(&self, keep: f64) -> f64 {
+        let p = self.probability_at_least(keep);
+        if p > 0.0 {
+            1.0 / p
+        } else {
+            0.0
+        }
+    }
+
+    /// Odds of scoring at least `keep` at least once within `flames` flames.
+    pub fn chance_within(&self, keep: f64, flames: i32) -> f64 {
+        1.0 - (1.0 - self.probability_at_least(keep)).powi(flames)
+    }
+
+    /// Expected flame score.
+    pub fn mean(&self) -> f64 {
+        self.pmf.iter().map(|(score, p)| score * p).sum()
+    }
+
+    /// Standard deviation of the flame score.
+    pub fn std_dev(&self) -> f64 {
+        let mean = self.mean();
+        self.pmf
+            .iter()
+            .map(|(score, p)| (score - mean).powi(2) * p)
+            .sum::<f64>()
+            .sqrt()
+    }
+
+    /// Lowest score that at least `fraction` of flames score at most, e.g. 0.9 for p90.
+    pub fn percentile(&self, fraction: f64) -> f64 {
+        let total: f64 = self.pmf.iter().map(|(_, p)| p).sum();
+        let mut below = 0.0;
+        for (score, p) in self.pmf.iter() {
+            below += p;
+            if below >= fraction * total {
+                return *score;
+            }
+        }
+        self.pmf.last().map_or(0.0, |(score, _)| *score)
+    }
+
+    /// Splits the score range into `bins` equally wide bins.
+    pub fn histogram(&self, bins: usize) -> Vec<Bin> {
+        let (Some((lowest, _)), Some((highest, _))) = (self.pmf.first(), self.pmf.last()) else {
+            return Vec::new();
+        };
+        let width = (highest - lowest) / bins as f64;
+        if width <= 0.0 {
+            return vec![Bin {
+                lower: *lowest,
+                upper: *highest,
+                probability: self.pmf.iter().map(|(_, p)| p).sum(),
+            }];
+        }
+
+        let mut histogram: Vec<Bin> = (0..bins)
+            .map(|i| Bin {
+                lower: lowest + width * i as f64,
+                upper: lowest + width * (i + 1) as f64,
+                probability: 0.0,
+            })
+            .collect();
+        for (score, p) in self.pmf.iter() {
+            let bin = (((score - lowest) / width) as usize).min(bins - 1);
+            histogram[bin].probability += p;
+        }
+        histogram
+    }
+
+    /// Distribution of the best score out of `flames` flames.
+    ///
+    /// The lowest scores are only best when every flame rolls them, which soon gets too
+    /// unlikely to show: their combined odds, while negligible, go to the first score above.
+    pub fn best_of(&self, flames: u32) -> ScoreDistribution {
+        // dividing by the summed mass keeps rounding from raising `at_most` past 1, or short of it
+        let total: f64 = self.pmf.iter().map(|(_, p)| p).sum();
+        let mut at_most = 0.0;
+        let mut previous = 0.0;
+        let pmf = self
+            .pmf
+            .iter()
+            .filter_map(|(score, p)| {
+                at_most += p;
+                let best_at_most = (at_most / total).powi(flames as i32);
+                if best_at_most <= NEGLIGIBLE_MASS {
+                    return None;
+                }
+                let best = best_at_most - previous;
+                previous = best_at_most;
+                Some((*score, best))
+            })
+            .collect();
+        ScoreDistribution { pmf }
+    }
+
+    /// Expected score of the better of a new flame and one scoring `floor`.
+    pub fn expected_max(&self, floor: f64) -> f64 {
+        self.pmf.iter().map(|(score, p)| score.max(floor) * p).sum()
+    }
+
+    /// Scores to stop rerolling at, by flames left, when every flame replaces the last one
+    /// and costs as much as `flame_cost` score points are worth.
+    ///
+    /// With `n` flames left, keeping a flame scoring at least `thresholds[n - 1]` is expected
+    /// to end higher than rerolling it, and rerolling anything below is expected to end higher
+    /// than keeping it. Each threshold is the expected final score of rerolling and then
+    /// following the thresholds, net of the flames it costs, so rerolling with `n` flames
+    /// left is worth `thresholds[n - 1]`.
+    pub fn stop_thresholds(&self, budget: u32, flame_cost: f64) -> Vec<f64> {
+        let above = self.suffix_sums();
+        let mut thresholds = Vec::with_capacity(budget as usize);
+        // with no flames left the flame has to be kept, whatever it scores
+        let mut next = f64::NEG_INFINITY;
+        for _ in 0..budget {
+            let first_above = self.pmf.partition_point(|(score, _)| *score <= next);
+            let (mass, total) = above[first_above];
+            next = if first_above == 0 {
+                total
+            } else {
+                total + next * (above[0].0 - mass)
+            } - flame_cost;
+            thresholds.push(next);
+        }
+        thresholds
+    }
+
+    /// Lowest score at which one more flame, keeping the better of it and the old one, is
+    /// expected to gain at most `flame_cost` points.
+    ///
+    /// The expected gain `expected_max(score) - score` falls as the score rises, linearly
+    /// between two scores of the distribution, so the threshold is found exactly.
+    pub fn gain_threshold(&self, flame_cost: f64) -> f64 {
+        let mut mass = 0.0;
+        let mut total = 0.0;
+        for (i, (score, p)) in self.pmf.iter().enumerate().rev() {
+            mass += p;
+            total += score * p;
+            // between the next lower score and this one, the gain is `total - s * mass`
+            let threshold = (total - flame_cost) / mass;
+            let below = i
+                .checked_sub(1)
+                .map_or(f64::NEG_INFINITY, |i| self.pmf[i].0);
+            if threshold >= below {
+                return threshold.min(*score);
+            }
+        }
+        f64::NEG_INFINITY
+    }
+
+    /// Best way to spend up to `budget` flames on an item scoring `current`, when every flame
+    /// costs as much as `flame_cost` score points are worth (0 if only the score matters).
+    ///
+    /// When every flame replaces the last one (`keep_old` false), the thresholds of
+    /// [`stop_thresholds`](Self::stop_thresholds) are worked out backwards from the last
+    /// flame. When the better of the old and new flame is kept, the score only goes up, and
+    /// flaming on pays off exactly while one more flame is expected to gain more than it costs:
+    /// the single threshold of [`gain_threshold`](Self::gain_threshold) holds whatever is left
+    /// of the budget.
+    pub fn stop_rule(
+        &self,
+        current: f64,
+        budget: u32,
+        keep_old: bool,
+        flame_cost: f64,
+    ) -> StopRule {
+        if keep_old {
+            self.keep_old_rule(current, budget, flame_cost)
+        } else {
+            self.replace_rule(current, budget, flame_cost)
+        }
+    }
+
+    fn replace_rule(&self, current: f64, budget: u32, flame_cost: f64) -> StopRule {
+        let thresholds = self.stop_thresholds(budget, flame_cost);
+        let above = self.suffix_sums();
+        // score and flames expected from rolling with n flames left, then following the rule
+        let (mut score, mut flames) = (0.0, 0.0);
+        for n in 0..thresholds.len() {
+            let first_kept = match n {
+                0 => 0,
+                _ => self.pmf.partition_point(|(x, _)| *x < thresholds[n - 1]),
+            };
+            let (kept, kept_total) = above[first_kept];
+            let rerolled = above[0].0 - kept;
+            score = kept_total + rerolled * score;
+            flames = 1.0 + rerolled * flames;
+        }
+        let keep_flaming = thresholds
+            .last()
+            .is_some_and(|threshold| current < *threshold);
+        StopRule {
+            keep_flaming,
+            expected_score: if keep_flaming { score } else { current },
+            expected_flames: if keep_flaming { flames } else { 0.0 },
+            thresholds,
+        }
+    }
+
+    fn keep_old_rule(&self, current: f64, budget: u32, flame_cost: f64) -> StopRule {
+        let threshold = self.gain_threshold(flame_cost);
+        if budget == 0 || current >= threshold {
+            return StopRule {
+                thresholds: vec![threshold],
+                keep_flaming: false,
+                expected_score: current,
+                expected_flames: 0.0,
+            };
+        }
+
+        // flaming stops at the first flame reaching the threshold, or when the budget runs out
+        let first_reached = self.pmf.partition_point(|(x, _)| *x < threshold);
+        let (reach, reach_total) = self.suffix_sums()[first_reached];
+        let missed_all = (1.0 - reach).powi(budget as i32);
+        let reached_score = if reach > 0.0 {
+            reach_total / reach
+        } else {
+            0.0
+        };
+        // missing every time, the flames all come from the scores below the threshold
+        let short_score = if missed_all > 0.0 {
+            let short = ScoreDistribution {
+                pmf: self.pmf[..first_reached]
+                    .iter()
+                    .map(|(score, p)| (*score, p / (1.0 - reach)))
+                    .collect(),
+            };
+            short.best_of(budget).expected_max(current)
+        } else {
+            current
+        };
+        StopRule {
+            thresholds: vec![threshold],
+            keep_flaming: true,
+            expected_score: (1.0 - missed_all) * reached_score + missed_all * short_score,
+            expected_flames: if reach > 0.0 {
+                (1.0 - missed_all) / reach
+            } else {
+                budget as f64
+            },
+        }
+    }
+
+    /// `(mass, mass * score)` of every score from each index up.
+    fn suffix_sums(&self) -> Vec<(f64, f64)> {
+        let mut above = vec![(0.0, 0.0); self.pmf.len() + 1];
+        for (i, (score, p)) in self.pmf.iter().enumerate().rev() {
+            above[i] = (above[i + 1].0 + p, above[i + 1].1 + score * p);
+        }
+        above
+    }
+
+    /// Builds a distribution from the total weight of every score key.
+    ///
+    /// Probabilities are normalized by the summed weight rather than the trial count, so the
+    /// reweighted rolls of importance sampling still add up to exactly 1.
+    pub(crate) fn from_weights<I>(weights: I) -> ScoreDistribution
+    where
+        I: IntoIterator<Item = (i64, f64)>,
+    {
+        let mut pmf: Vec<(f64, f64)> = weights
+            .into_iter()
+            .filter(|(_, weight)| *weight > 0.0)
+            .map(|(key, weight)| (from_key(key), weight))
+            .collect();
+        pmf.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let total: f64 = pmf.iter().map(|(_, weight)| weight).sum();
+        for (_, weight) in pmf.iter_mut() {
+            *weight /= total;
+        }
+        ScoreDistribution { pmf }
+    }
+}
+
+fn binomial(n: usize, k: usize) -> f64 {
+    (0..k).fold(1.0, |acc, i| acc * (n - i) as f64 / (i + 1) as f64)
+}
+
+impl FlameSimulator {
+    /// Score contribution of each tier of a single line, merged by score.
+    fn line_distribution(&self, option: &str, values: &[u16]) -> BTreeMap<i64, f64> {
+        let weights = self.line_tier_weights();
+        let total: f64 = weights.iter().map(|w| *w as f64).sum();
+        let mut dist = BTreeMap::new();
+
+        for (value, weight) in values.iter().zip(weights) {
+            if *weight <= 0.0 {
+                continue;
+            }
+            let mut stats = [0; FLAME_STATS.len()];
+            add_line(&mut stats, option, *value);
+            let score = self.formula().score_stats(&stats) as f64;
+            *dist.entry(to_key(score)).or_insert(0.0) += *weight as f64 / total;
+        }
+        dist
+    }
+
+    /// Computes the exact score distribution of a single flame.
+    pub fn exact_distribution(&self) -> ScoreDistribution {
+        let line_counts: Vec<(usize, f64)> = if self.noboss {
+            let total: f64 = NOBOSS_LINE_WEIGHTS.iter().map(|w| *w as f64).sum();
+            NOBOSS_LINES
+                .iter()
+                .zip(NOBOSS_LINE_WEIGHTS)
+                .map(|(lines, weight)| (*lines, weight as f64 / total))
+                .collect()
+        } else {
+            vec![(4, 1.0)]
+        };
+        let max_lines = line_counts
+            .iter()
+            .map(|(lines, _)| *lines)
+            .max()
+            .unwrap_or(0);
+
+        // subsets[j] is the score distribution summed over every j-line subset seen so far
+        let mut subsets: Vec<BTreeMap<i64, f64>> = vec![BTreeMap::new(); max_lines + 1];
+        subsets[0].insert(0, 1.0);
+
+        for option in self.option_table.iter() {
+            let line = self.line_distribution(option.n, &option.v);
+            for j in (0..max_lines).rev() {
+                let mut next = BTreeMap::new();
+                for (score, p) in subsets[j].iter() {
+                    for (line_score, q) in line.iter() {
+                        *next.entry(score + line_score).or_insert(0.0) += p * q;
+                    }
+                }
+                for (score, p) in next {
+                    *subsets[j + 1].entry(score).or_insert(0.0) += p;
+                }
+            }
+        }
+
+        let options = self.option_table.len();
+        let mut pmf: BTreeMap<i64, f64> = BTreeMap::new();
+        for (lines, weight) in line_counts {
+            let subset_count = binomial(options, lines);
+            for (score, p) in subsets[lines].iter() {
+                *pmf.entry(*score).or_insert(0.0) += weight * p / subset_count;
+            }
+        }
+
+        ScoreDistribution {
+            pmf: pmf
+                .into_iter()
+                .filter(|(_, p)| *p > 0.0)
+                .map(|(score, p)| (from_key(score), p))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ScoreDistribution;
+    use crate::fixtures::eflame_simulator;
+
+    #[test]
+    fn pmf_sums_to_one() {
+        for noboss in [false, true] {
+            let distribution = eflame_simulator(noboss).exact_distribution();
+            let total: f64 = distribution.pmf.iter().map(|(_, p)| p).sum();
+            assert!(
+                (total - 1.0).abs() < 1e-9,
+                "noboss {}: total mass {}",
+                noboss,
+                total
+            );
+        }
+    }
+
+    #[test]
+    fn matches_simulation() {
+        // targets hit by about 1% of flames, so a seeded run hits them a few thousand times
+        for (noboss, keep) in [(false, 130.0), (true, 60.0)] {
+            let simulator = eflame_simulator(noboss);
+            let exact = simulator
+                .exact_distribution()
+                .probability_at_least(keep as f64);
+            let interval = simulator
+                .run(200_000, keep, 0, 1, |_| {})
+                .probability_interval();
+            assert!(
+                interval.lower <= exact && exact <= interval.upper,
+                "noboss {}: exact {} outside {:?}",
+                noboss,
+                exact,
+                interval
+            );
+        }
+    }
+
+    /// Three scores, with a gap wide enough for a keep-old threshold to fall inside.
+    fn small() -> ScoreDistribution {
+        ScoreDistribution {
+            pmf: vec![(0.0, 0.5), (10.0, 0.3), (30.0, 0.2)],
+        }
+    }
+
+    /// Value net of flame costs, expected final score and expected flames of flaming optimally,
+    /// by trying both stopping and rolling every score at every step.
+    fn brute_force(
+        distribution: &ScoreDistribution,
+        current: f64,
+        left: u32,
+        keep_old: bool,
+        flame_cost: f64,
+    ) -> (f64, f64, f64) {
+        let stop = (current, current, 0.0);
+        if left == 0 {
+            return stop;
+        }
+        let mut roll = (-flame_cost, 0.0, 1.0);
+        for (score, p) in &distribution.pmf {
+            let next = if keep_old { score.max(current) } else { *score };
+            let (value, final_score, flames) =
+                brute_force(distribution, next, left - 1, keep_old, flame_cost);
+            roll.0 += p * value;
+            roll.1 += p * final_score;
+            roll.2 += p * flames;
+        }
+        if roll.0 > stop.0 {
+            roll
+        } else {
+            stop
+        }
+    }
+
+    fn assert_close(actual: f64, expected: f64, what: &str) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{}: {} instead of {}",
+            what,
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn stop_thresholds_match_brute_force() {
+        let distribution = small();
+        for flame_cost in [0.0, 2.5] {
+            let thresholds = distribution.stop_thresholds(3, flame_cost);
+            for (left, threshold) in (1..=3).zip(&thresholds) {
+                // rolling is worth the same whatever the current flame scores
+                let (value, _, _) = brute_force(&distribution, -1e9, left, false, flame_cost);
+                assert_close(*threshold, value, "threshold");
+            }
+        }
+    }
+
+    #[test]
+    fn replace_rule_matches_brute_force() {
+        let distribution = small();
+        for flame_cost in [0.0, 2.5] {
+            for budget in 1..=3 {
+                for current in [0.0, 5.0, 10.0, 12.0, 30.0] {
+                    let rule = distribution.stop_rule(current, budget, false, flame_cost);
+                    let (_, score, flames) =
+                        brute_force(&distribution, current, budget, false, flame_cost);
+                    assert_eq!(rule.keep_flaming, flames > 0.0, "current {}", current);
+                    assert_close(rule.expected_score, score, "expected score");
+                    assert_close(rule.expected_flames, flames, "expected flames");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn gain_threshold_falls_between_scores() {
+        let distribution = small();
+        // free flames are worth rolling until nothing can beat the item
+        assert_close(distribution.gain_threshold(0.0), 30.0, "free flames");
+        // above 10, a flame gains 0.2 * (30 - score), which is 1 at 25
+        assert_close(distribution.gain_threshold(1.0), 25.0, "flames costing 1");
+        // below 10, it gains 0.3 * (10 - score) + 0.2 * (30 - score), which is 7 at 4
+        assert_close(distribution.gain_threshold(7.0), 4.0, "flames costing 7");
+    }
+
+    #[test]
+    fn keep_old_rule_matches_brute_force() {
+        let distribution = small();
+        for flame_cost in [0.0, 1.0, 7.0] {
+            for budget in 1..=3 {
+                for current in [0.0, 3.0, 5.0, 10.0, 20.0, 25.0, 26.0, 30.0] {
+                    let rule = distribution.stop_rule(current, budget, true, flame_cost);
+                    let (_, score, flames) =
+                        brute_force(&distribution, current, budget, true, flame_cost);
+                    assert_eq!(rule.keep_flaming, flames > 0.0, "current {}", current);
+                    assert_close(rule.expected_score, score, "expected score");
+                    assert_close(rule.expected_flames, flames, "expected flames");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn best_of_one_is_the_distribution() {
+        let distribution = eflame_simulator(false).exact_distribution();
+        let best = distribution.best_of(1);
+        assert_eq!(best.pmf.len(), distribution.pmf.len());
+        for ((score, p), (best_score, best_p)) in distribution.pmf.iter().zip(&best.pmf) {
+            assert_eq!(score, best_score);
+            assert_close(*best_p, *p, "mass");
+        }
+    }
+
+    #[test]
+    fn best_of_reaches_scores_like_independent_flames() {
+        let distribution = eflame_simulator(false).exact_distribution();
+        for flames in [2, 50, 5_000] {
+            let best = distribution.best_of(flames);
+            let total: f64 = best.pmf.iter().map(|(_, p)| p).sum();
+            assert_close(total, 1.0, "total mass");
+            // the highest score stays reachable however many flames there are
+            let top = |distribution: &ScoreDistribution| distribution.pmf.last().unwrap().0;
+            assert_eq!(top(&best), top(&distribution));
+            for (score, _) in &best.pmf {
+                let p = distribution.probability_at_least(*score);
+                let expected = 1.0 - (1.0 - p).powi(flames as i32);
+                let actual = best.probability_at_least(*score);
+                assert!(
+                    (actual - expected).abs() < 1e-9,
+                    "{} flames, score {}: {} instead of {}",
+                    flames,
+                    score,
+                    actual,
+                    expected
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! Simulators shared by the tests of several modules.
+
+use crate::score::{ScoreFormula, ScoreWeights};
+use crate::simulator::{FlameSimulator, ItemType};
+use crate::tables::GameTables;
+use crate::types::{FlameType, JobStat, LevelBracket};
+
+/// Eternal flames on level 200 armor, scored for a STR class with the default weights.
+pub(crate) fn eflame_simulator(noboss: bool) -> FlameSimulator {
+    FlameSimulator::new(
+        ScoreFormula::preset(JobStat::Str, &ScoreWeights::default()),
+        LevelBracket::from_level(200).unwrap(),
+        FlameType::Eflame,
+        noboss,
+        ItemType::Armor,
+        &GameTables::bundled(),
+    )
+    .unwrap()
+}
@@ -0,0 +1,81 @@
+//! A single rolled flame and its score.
+
+/// Stats recorded on a flame, in display order.
+pub const FLAME_STATS: [&str; 15] = [
+    "str", "dex", "int", "luk", "att", "matt", "hp", "mp", "jmp", "speed", "as", "boss", "dmg",
+    "def", "lvlred",
+];
+
+/// Stat totals rolled on a flame, indexed like [`FLAME_STATS`].
+///
+/// Flames are rolled and scored in this form, so a run doesn't allocate per flame; the named
+/// [`Flame`] is only built for flames that are kept.
+pub(crate) type Stats = [u16; FLAME_STATS.len()];
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Flame {
+    /// Stat totals, one entry per name in [`FLAME_STATS`].
+    pub lines: Vec<(&'static str, u16)>,
+    pub score: f32,
+}
+
+impl Flame {
+    pub(crate) fn from_stats(stats: &Stats, score: f32) -> Flame {
+        Flame {
+            lines: FLAME_STATS
+                .iter()
+                .copied()
+                .zip(stats.iter().copied())
+                .collect(),
+            score,
+        }
+    }
+
+    /// Returns the total of a stat on this flame, or 0 if the stat isn't tracked.
+    pub fn get(&self, stat: &str) -> u16 {
+        self.lines
+            .iter()
+            .find(|line| line.0 == stat)
+            .map_or(0, |line| line.1)
+    }
+}
+
+/// Adds a rolled line to the stat totals.
+pub(crate) fn add_line(stats: &mut Stats, option: &str, value: u16) {
+    add_stats(stats, line_stats(option), value);
+}
+
+/// Adds `value` to the stats at the given [`FLAME_STATS`] indexes.
+pub(crate) fn add_stats(stats: &mut Stats, indexes: &[usize], value: u16) {
+    for i in indexes {
+        stats[*i] += value;
+    }
+}
+
+/// Indexes into [`FLAME_STATS`] of the stats a line adds to; combo lines add to two stats.
+pub(crate) fn line_stats(option: &str) -> &'static [usize] {
+    match option {
+        "str" => &[0],
+        "strdex" => &[0, 1],
+        "strint" => &[0, 2],
+        "strluk" => &[0, 3],
+        "dex" => &[1],
+        "dexint" => &[1, 2],
+        "dexluk" => &[1, 3],
+        "int" => &[2],
+        "lukint" => &[2, 3],
+        "luk" => &[3],
+        "att" => &[4],
+        "matt" => &[5],
+        "hp" => &[6],
+        "mp" => &[7],
+        "jmp" => &[8],
+        "speed" => &[9],
+        "as" => &[10],
+        "boss" => &[11],
+        "dmg" => &[12],
+        "def" => &[13],
+        "lvlred" => &[14],
+        _ => &[],
+    }
+}
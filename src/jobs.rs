@@ -0,0 +1,129 @@
+//! Job presets: every class mapped to the stat archetype its flames are scored for.
+
+use crate::types::JobStat;
+use clap::ValueEnum;
+
+/// A playable class, selectable with `--job`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Job {
+    // warriors
+    Hero,
+    Paladin,
+    DarkKnight,
+    DawnWarrior,
+    Mihile,
+    Aran,
+    DemonSlayer,
+    DemonAvenger,
+    Blaster,
+    Kaiser,
+    Adele,
+    Hayato,
+    Zero,
+    Len,
+    // magicians
+    ArchMageFp,
+    ArchMageIl,
+    Bishop,
+    BlazeWizard,
+    Evan,
+    Luminous,
+    BattleMage,
+    Kanna,
+    Illium,
+    Lara,
+    Kinesis,
+    BeastTamer,
+    Lynn,
+    SiaAstelle,
+    // bowmen
+    Bowmaster,
+    Marksman,
+    Pathfinder,
+    WindArcher,
+    Mercedes,
+    WildHunter,
+    Kain,
+    // thieves
+    NightLord,
+    Shadower,
+    DualBlade,
+    NightWalker,
+    Phantom,
+    Cadena,
+    Khali,
+    Hoyoung,
+    MoXuan,
+    Xenon,
+    // pirates
+    Buccaneer,
+    Corsair,
+    Cannoneer,
+    ThunderBreaker,
+    Mechanic,
+    AngelicBuster,
+    Shade,
+    Ark,
+}
+
+impl Job {
+    /// Stat archetype the job's flames are scored for.
+    ///
+    /// The archetype decides the main stat, secondary stats and attack type, e.g. Shadower
+    /// uses LUK with STR and DEX secondaries and Demon Avenger uses flat HP.
+    pub fn stat(self) -> JobStat {
+        match self {
+            Job::Hero
+            | Job::Paladin
+            | Job::DarkKnight
+            | Job::DawnWarrior
+            | Job::Mihile
+            | Job::Aran
+            | Job::DemonSlayer
+            | Job::Blaster
+            | Job::Kaiser
+            | Job::Adele
+            | Job::Hayato
+            | Job::Zero
+            | Job::Len
+            | Job::Buccaneer
+            | Job::Cannoneer
+            | Job::ThunderBreaker
+            | Job::Shade
+            | Job::Ark => JobStat::Str,
+            Job::ArchMageFp
+            | Job::ArchMageIl
+            | Job::Bishop
+            | Job::BlazeWizard
+            | Job::Evan
+            | Job::Luminous
+            | Job::BattleMage
+            | Job::Illium
+            | Job::Lara
+            | Job::Kinesis
+            | Job::BeastTamer
+            | Job::Lynn
+            | Job::SiaAstelle => JobStat::Int,
+            Job::Bowmaster
+            | Job::Marksman
+            | Job::Pathfinder
+            | Job::WindArcher
+            | Job::Mercedes
+            | Job::WildHunter
+            | Job::Kain
+            | Job::Corsair
+            | Job::Mechanic
+            | Job::AngelicBuster => JobStat::Dex,
+            Job::NightLord
+            | Job::NightWalker
+            | Job::Phantom
+            | Job::Khali
+            | Job::Hoyoung
+            | Job::MoXuan => JobStat::Luk,
+            Job::Shadower | Job::DualBlade | Job::Cadena => JobStat::AltThief,
+            Job::DemonAvenger => JobStat::Da,
+            Job::Kanna => JobStat::Kanna,
+            Job::Xenon => JobStat::Xenon,
+        }
+    }
+}
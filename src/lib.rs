@@ -0,0 +1,29 @@
+//! Flame simulator library.
+//!
+//! Rolls bonus-stat flames for a given equip level, flame type and item type and scores them
+//! with a class preset or a custom formula (see `flame_values.json`).
+
+pub mod confidence;
+pub mod exact;
+#[cfg(test)]
+mod fixtures;
+pub mod flame;
+pub mod jobs;
+pub mod report;
+mod sampler;
+pub mod score;
+pub mod simulator;
+pub mod tables;
+pub mod types;
+
+pub use confidence::Interval;
+pub use exact::{Bin, ScoreDistribution, StopRule};
+pub use flame::Flame;
+pub use jobs::Job;
+pub use report::Report;
+pub use score::{ScoreError, ScoreFormula, ScoreWeights};
+pub use simulator::{
+    Error, FlameSimulator, ItemType, Precision, SimulationResult, ThresholdCount, Weights,
+};
+pub use tables::{FlameCost, GameTables, Price, TableError};
+pub use types::{EquipLevel, FlameType, JobStat, LevelBracket};
@@ -1,755 +1,2361 @@
-use clap::{arg, command, value_parser, Arg, ArgAction};
-use config::{Config, File, FileFormat};
-use indicatif::ProgressBar;
-use rand::seq::SliceRandom;
-use random_choice::random_choice;
-use rayon::prelude::*;
-use std::{cmp::Ordering, env, io, path::PathBuf, sync::Mutex, time::Instant};
-use thousands::Separable;
-
-#[derive(Clone)]
-struct Item<T> {
-    n: &'static str,
-    v: Vec<T>,
-}
-
-fn build_flame(
-    stat: &str,
-    option_table: Vec<Item<Item<u16>>>,
-    flamestat: &str,
-    noboss: bool,
-    allstat: f32,
-    substat: f32,
-    att: f32,
-    hpmp: f32,
-) -> (Vec<(&'static str, u16)>, f32) {
-    let tier_weights: Vec<Item<f32>> = vec![
-        Item {
-            n: "abyss",
-            v: vec![0.0, 0.0, 0.0, 0.0, 0.63, 0.34, 0.03, 0.0, 0.0],
-        },
-        Item {
-            n: "totem",
-            v: vec![0.0, 0.0, 0.558, 0.325, 0.065, 0.032, 0.02, 0.0, 0.0],
-        },
-        Item {
-            n: "drop",
-            v: vec![0.0, 0.0, 0.25, 0.3, 0.3, 0.14, 0.01, 0.0, 0.0],
-        },
-        Item {
-            n: "pflame",
-            v: vec![0.0, 0.0, 0.2, 0.3, 0.36, 0.14, 0.0, 0.0, 0.0],
-        },
-        Item {
-            n: "eflame",
-            v: vec![0.0, 0.0, 0.0, 0.29, 0.45, 0.25, 0.01, 0.0, 0.0],
-        },
-        Item {
-            n: "regcraft",
-            v: vec![0.0, 0.0, 0.5, 0.4, 0.1, 0.0, 0.0, 0.0, 0.0],
-        },
-        Item {
-            n: "mastercraft",
-            v: vec![0.0, 0.0, 0.15, 0.3, 0.4, 0.14, 0.01, 0.0, 0.0],
-        },
-        Item {
-            n: "meistercraft",
-            v: vec![0.0, 0.0, 0.0, 0.19, 0.5, 0.3, 0.01, 0.0, 0.0],
-        },
-        Item {
-            n: "masterfuse",
-            v: vec![0.0, 0.0, 0.25, 0.35, 0.3, 0.1, 0.0, 0.0, 0.0],
-        },
-        Item {
-            n: "meisterfuse",
-            v: vec![0.0, 0.0, 0.0, 0.4, 0.45, 0.14, 0.01, 0.0, 0.0],
-        },
-    ]
-    .into_iter()
-    .filter(|a| a.n == flamestat)
-    .collect();
-
-    // return values
-    let mut score = 0.0;
-    let mut flame = vec![
-        ("str", 0),
-        ("dex", 0),
-        ("int", 0),
-        ("luk", 0),
-        ("att", 0),
-        ("matt", 0),
-        ("hp", 0),
-        ("mp", 0),
-        ("jmp", 0),
-        ("speed", 0),
-        ("as", 0),
-    ];
-
-    // line weight values
-    let mut weights = &tier_weights[0].v[..7];
-    let mut lines = 4;
-
-    // set noboss line weight values
-    if noboss {
-        weights = &tier_weights[0].v[2..];
-        let a = [1, 2, 3, 4];
-        let lineweights = [0.39, 0.39, 0.18, 0.04];
-        let choice = random_choice().random_choice_f32(&a, &lineweights, 1);
-        lines = *choice[0];
-    }
-
-    // build flame
-    for option in option_table
-        .choose_multiple(&mut rand::thread_rng(), lines)
-        .into_iter()
-    {
-        // chooses 4 random flame_template from option table
-        for choice in random_choice().random_choice_f32(&option.v[0].v, weights, 1) {
-            // chooses tier by weight
-            // add chosen line to flame
-            if option.n == "str" {
-                flame[0].1 += *choice;
-            } else if option.n == "strdex" {
-                flame[0].1 += *choice;
-                flame[1].1 += *choice;
-            } else if option.n == "strint" {
-                flame[0].1 += *choice;
-                flame[2].1 += *choice;
-            } else if option.n == "strluk" {
-                flame[0].1 += *choice;
-                flame[3].1 += *choice;
-            } else if option.n == "dex" {
-                flame[1].1 += *choice;
-            } else if option.n == "dexint" {
-                flame[1].1 += *choice;
-                flame[2].1 += *choice;
-            } else if option.n == "dexluk" {
-                flame[1].1 += *choice;
-                flame[3].1 += *choice;
-            } else if option.n == "int" {
-                flame[2].1 += *choice;
-            } else if option.n == "lukint" {
-                flame[2].1 += *choice;
-                flame[3].1 += *choice;
-            } else if option.n == "luk" {
-                flame[3].1 += *choice;
-            } else if option.n == "att" {
-                flame[4].1 += *choice;
-            } else if option.n == "matt" {
-                flame[5].1 += *choice;
-            } else if option.n == "hp" {
-                flame[6].1 += *choice;
-            } else if option.n == "mp" {
-                flame[7].1 += *choice;
-            } else if option.n == "jmp" {
-                flame[8].1 += *choice;
-            } else if option.n == "speed" {
-                flame[9].1 += *choice;
-            } else if option.n == "as" {
-                flame[10].1 += *choice;
-            }
-            // score flame
-            if stat == "str" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[0].1 as f32
-                    + flame[1].1 as f32 * substat;
-            } else if stat == "luk" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[3].1 as f32
-                    + flame[1].1 as f32 * substat;
-            } else if stat == "dex" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[1].1 as f32
-                    + flame[0].1 as f32 * substat;
-            } else if stat == "int" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[5].1 as f32 * att
-                    + flame[2].1 as f32
-                    + flame[3].1 as f32 * substat;
-            } else if stat == "kanna" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[5].1 as f32 * att
-                    + flame[2].1 as f32
-                    + flame[3].1 as f32 * substat
-                    + flame[6].1 as f32 / hpmp
-                    + flame[7].1 as f32 / hpmp
-            } else if stat == "da" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[6].1 as f32
-                    + flame[0].1 as f32 * substat
-            } else if stat == "alt_thief" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[3].1 as f32
-                    + flame[0].1 as f32 * substat
-                    + flame[1].1 as f32 * substat
-            } else if stat == "xenon" {
-                score = flame[10].1 as f32 * allstat
-                    + flame[4].1 as f32 * att
-                    + flame[0].1 as f32
-                    + flame[1].1 as f32
-                    + flame[3].1 as f32
-            }
-        }
-    }
-    (flame, score) // add score return value
-}
-
-fn inner_main() -> io::Result<PathBuf> {
-    let mut exe = env::current_exe()?;
-    exe.set_file_name("flame_values.json");
-    Ok(exe)
-}
-
-fn main() {
-    let mut dir = String::new();
-
-    match inner_main() {
-        Ok(path) => dir = path.as_path().display().to_string(),
-        Err(e) => {
-            eprintln!("Error determining executable path: {}", e);
-        }
-    }
-
-    let builder = Config::builder().add_source(File::new(&dir, FileFormat::Json));
-
-    let mut allstat = 8.0;
-    let mut allstat_x = 20.0; // xenon
-    let mut substat = 0.10;
-    let mut att = 3.0;
-    let mut att_d = 20.0; // da
-    let mut att_x = 6.0; // xenon
-    let mut hpmp = 120.0; // kanna
-
-    match builder.build() {
-        Ok(config) => {
-            allstat = config.get_float("allstat").unwrap() as f32;
-            allstat_x = config.get_float("allstat_x").unwrap() as f32;
-            substat = config.get_float("substat").unwrap() as f32;
-            att = config.get_float("att").unwrap() as f32;
-            att_d = config.get_float("att_d").unwrap() as f32;
-            att_x = config.get_float("att_x").unwrap() as f32;
-            hpmp = config.get_float("hpmp").unwrap() as f32;
-        }
-        Err(e) => {
-            println!("error parsing: {:?}", e);
-        }
-    }
-
-    let matches = command!()
-    .arg(
-        arg!(
-            -t --trials <TRIALS> "Amount of times to run the simulator"
-        )
-        .value_parser(value_parser!(u64))
-        .default_value("100000"),
-    )
-    .arg(
-        arg!(
-            -s --stat <STAT> "Stat to roll for [options: str, dex, int, luk, kanna, da, xenon, alt_thief]"
-        )
-        .value_parser(value_parser!(String))
-        .default_value("str"),
-    )
-    .arg(
-        arg!(
-            -l --level <LEVEL> "Equip level [options: 100-109, 110-119, 120-129, 130-139, 140-149, 150-159, 160-169, 170-179, 180-189, 190-199, 200-249, 250+]"
-        )
-        .value_parser(value_parser!(String))
-        .default_value("140-149"),
-    )
-    .arg(
-        arg!(
-            -k --keep <THRESHOLD> "Minimum flamescore target"
-        )
-        .value_parser(value_parser!(f32))
-        .default_value("100"),
-    )
-    .arg(
-        arg!(
-            -f --flametype <FLAMETYPE> "Type of flame used [options: abyss, totem, drop, pflame, eflame, regcraft, mastercraft, meistercraft, masterfuse, meisterfuse]"
-        )
-        .value_parser(value_parser!(String))
-        .default_value("pflame"),
-    )
-    .arg(
-        arg!(
-            --top <NUMBER> "Displays the top scoring flames (max 1000)"
-        )
-        .value_parser(value_parser!(usize))
-        .required(false)
-    )
-    .arg(
-        arg!(
-            -c --chance <NUMBER> "Calculates the odds of getting target flame within the specified amount of flames"
-        )
-        .value_parser(value_parser!(u64))
-        .required(false)
-    )
-    .arg(
-        Arg::new("noboss")
-            .short('n')
-            .long("noboss")
-            .help("Simulate non-boss flames")
-            .action(ArgAction::SetTrue),
-    )
-    .get_matches();
-
-    let trials = matches.get_one::<u64>("trials").unwrap();
-    let stat = matches.get_one::<String>("stat").unwrap().to_owned();
-    let keep = matches.get_one::<f32>("keep").unwrap();
-    let level = matches.get_one::<String>("level").unwrap();
-    let flametype = matches.get_one::<String>("flametype").unwrap();
-
-    if stat == "da" {
-        att = att_d;
-    } else if stat == "xenon" {
-        att = att_x;
-        allstat = allstat_x;
-    }
-
-    let mut top: usize = 1;
-    if let Some(tops) = matches.get_one::<usize>("top") {
-        if *tops > 1000 {
-            top = 1000;
-        } else if *tops > *trials as usize {
-            top = *trials as usize;
-        } else {
-            top = *tops;
-        }
-    }
-
-    let mut chance: i32 = 0;
-    if let Some(budget) = matches.get_one::<i32>("chance") {
-        chance = *budget;
-    }
-
-    let mut noboss = false;
-    if matches.get_flag("noboss") {
-        noboss = matches.get_flag("noboss");
-    }
-
-    let now = Instant::now();
-
-    let flame_collection: Mutex<Vec<(Vec<(&str, u16)>, f32)>> = Mutex::new(Vec::new());
-    let count = Mutex::new(0);
-
-    let flat_options: Vec<Item<u16>> = vec![
-        Item {
-            n: "100-109",
-            v: vec![6, 12, 18, 24, 30, 36, 42],
-        },
-        Item {
-            n: "110-119",
-            v: vec![6, 12, 18, 24, 30, 36, 42],
-        },
-        Item {
-            n: "120-129",
-            v: vec![7, 14, 21, 28, 35, 42, 49],
-        },
-        Item {
-            n: "130-139",
-            v: vec![7, 14, 21, 28, 35, 42, 49],
-        },
-        Item {
-            n: "140-149",
-            v: vec![8, 16, 24, 32, 40, 48, 56],
-        },
-        Item {
-            n: "150-159",
-            v: vec![8, 16, 24, 32, 40, 48, 56],
-        },
-        Item {
-            n: "160-169",
-            v: vec![9, 18, 27, 36, 45, 54, 63],
-        },
-        Item {
-            n: "170-179",
-            v: vec![9, 18, 27, 36, 45, 54, 63],
-        },
-        Item {
-            n: "180-189",
-            v: vec![10, 20, 30, 40, 50, 60, 70],
-        },
-        Item {
-            n: "190-199",
-            v: vec![10, 20, 30, 40, 50, 60, 70],
-        },
-        Item {
-            n: "200-249",
-            v: vec![11, 22, 33, 44, 55, 66, 77],
-        },
-        Item {
-            n: "250+",
-            v: vec![12, 24, 36, 48, 60, 72, 84],
-        },
-    ]
-    .into_par_iter()
-    .filter(|a| a.n == level)
-    .collect();
-    let combo_options: Vec<Item<u16>> = vec![
-        Item {
-            n: "100-109",
-            v: vec![3, 6, 9, 12, 15, 18, 21],
-        },
-        Item {
-            n: "110-119",
-            v: vec![3, 6, 9, 12, 15, 18, 21],
-        },
-        Item {
-            n: "120-129",
-            v: vec![4, 8, 12, 16, 20, 24, 28],
-        },
-        Item {
-            n: "130-139",
-            v: vec![4, 8, 12, 16, 20, 24, 28],
-        },
-        Item {
-            n: "140-149",
-            v: vec![4, 8, 12, 16, 20, 24, 28],
-        },
-        Item {
-            n: "150-159",
-            v: vec![4, 8, 12, 16, 20, 24, 28],
-        },
-        Item {
-            n: "160-169",
-            v: vec![5, 10, 15, 20, 25, 30, 35],
-        },
-        Item {
-            n: "170-179",
-            v: vec![5, 10, 15, 20, 25, 30, 35],
-        },
-        Item {
-            n: "180-189",
-            v: vec![5, 10, 15, 20, 25, 30, 35],
-        },
-        Item {
-            n: "190-199",
-            v: vec![5, 10, 15, 20, 25, 30, 35],
-        },
-        Item {
-            n: "200-249",
-            v: vec![6, 12, 18, 24, 30, 36, 42],
-        },
-        Item {
-            n: "250+",
-            v: vec![7, 14, 21, 28, 35, 42, 49],
-        },
-    ]
-    .into_par_iter()
-    .filter(|a| a.n == level)
-    .collect();
-    let hpmp_options: Vec<Item<u16>> = vec![
-        Item {
-            n: "100-109",
-            v: vec![300, 600, 900, 1200, 1500, 1800, 2100],
-        },
-        Item {
-            n: "110-119",
-            v: vec![330, 660, 990, 1320, 1650, 1980, 2310],
-        },
-        Item {
-            n: "120-129",
-            v: vec![360, 720, 1080, 1440, 1800, 2160, 2520],
-        },
-        Item {
-            n: "130-139",
-            v: vec![390, 780, 1170, 1560, 1950, 2340, 2730],
-        },
-        Item {
-            n: "140-149",
-            v: vec![420, 840, 1260, 1680, 2100, 2520, 2940],
-        },
-        Item {
-            n: "150-159",
-            v: vec![450, 900, 1350, 1800, 2250, 2700, 3150],
-        },
-        Item {
-            n: "160-169",
-            v: vec![480, 960, 1440, 1920, 2400, 2880, 3360],
-        },
-        Item {
-            n: "170-179",
-            v: vec![510, 1020, 1530, 2040, 2550, 3060, 3570],
-        },
-        Item {
-            n: "180-189",
-            v: vec![540, 1080, 1620, 2160, 2700, 3240, 3780],
-        },
-        Item {
-            n: "190-199",
-            v: vec![570, 1140, 1710, 2280, 2850, 3420, 3990],
-        },
-        Item {
-            n: "200-249",
-            v: vec![600, 1200, 1800, 2400, 3000, 3600, 4200],
-        },
-        Item {
-            n: "250+",
-            v: vec![700, 1400, 2100, 2800, 3500, 4200, 4900],
-        },
-    ]
-    .into_par_iter()
-    .filter(|a| a.n == level)
-    .collect();
-    let basic_options: Vec<Item<u16>> = vec![
-        Item {
-            n: "100-109",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "110-119",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "120-129",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "130-139",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "140-149",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "150-159",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "160-169",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "170-179",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "180-189",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "190-199",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "200-249",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-        Item {
-            n: "250+",
-            v: vec![1, 2, 3, 4, 5, 6, 7],
-        },
-    ]
-    .into_par_iter()
-    .filter(|a| a.n == level)
-    .collect();
-    let option_table: Vec<Item<Item<u16>>> = vec![
-        Item {
-            n: "str",
-            v: flat_options.to_vec(),
-        },
-        Item {
-            n: "dex",
-            v: flat_options.to_vec(),
-        },
-        Item {
-            n: "int",
-            v: flat_options.to_vec(),
-        },
-        Item {
-            n: "luk",
-            v: flat_options.to_vec(),
-        },
-        Item {
-            n: "strdex",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "strint",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "strluk",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "dexint",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "dexluk",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "lukint",
-            v: combo_options.to_vec(),
-        },
-        Item {
-            n: "hp",
-            v: hpmp_options.to_vec(),
-        },
-        Item {
-            n: "mp",
-            v: hpmp_options.to_vec(),
-        },
-        Item {
-            n: "def",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "lvlred",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "att",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "matt",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "spd",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "jmp",
-            v: basic_options.to_vec(),
-        },
-        Item {
-            n: "as",
-            v: basic_options.to_vec(),
-        },
-    ];
-
-    let bar = ProgressBar::new(*trials);
-
-    (0..*trials).into_par_iter().for_each(|_| {
-        bar.inc(1);
-        let flame = build_flame(
-            &stat,
-            option_table.clone(),
-            flametype,
-            noboss,
-            allstat,
-            substat,
-            att,
-            hpmp,
-        );
-
-        if flame.1 >= *keep {
-            *count.lock().unwrap() += 1;
-        }
-
-        if flame_collection.lock().unwrap().len() < top {
-            flame_collection.lock().unwrap().push(flame.clone());
-            flame_collection.lock().unwrap().sort_by(
-                |a: &(Vec<(&str, u16)>, f32), b: &(Vec<(&str, u16)>, f32)| {
-                    // reverse sort collection by score
-                    if a.1 < b.1 {
-                        Ordering::Greater
-                    } else if a.1 == b.1 {
-                        Ordering::Equal
-                    } else {
-                        Ordering::Less
-                    }
-                },
-            );
-        } else if flame.1 > flame_collection.lock().unwrap()[top - 1].1 {
-            flame_collection.lock().unwrap().push(flame.clone());
-            flame_collection.lock().unwrap().sort_by(
-                |a: &(Vec<(&str, u16)>, f32), b: &(Vec<(&str, u16)>, f32)| {
-                    // reverse sort collection by score
-                    if a.1 < b.1 {
-                        Ordering::Greater
-                    } else if a.1 == b.1 {
-                        Ordering::Equal
-                    } else {
-                        Ordering::Less
-                    }
-                },
-            );
-            flame_collection.lock().unwrap().truncate(top);
-        }
-    });
-
-    bar.finish();
-
-    let mut average_flames: f32 = 0.0;
-
-    if *count.lock().unwrap() > 0 {
-        average_flames = *trials as f32 / *count.lock().unwrap() as f32;
-    }
-
-    println!(
-        "Settings - Trials: {}, Flametype: {}, Stat: {}, Level: {} \n",
-        trials.separate_with_commas(),
-        flametype,
-        stat,
-        level
-    );
-    if noboss {
-        println!("Noboss: {}", noboss)
-    }
-    println!("Results:");
-    println!(
-        "Flames over {} flamescore: {}/{} \n",
-        *keep,
-        count.lock().unwrap().clone().separate_with_commas(),
-        trials.separate_with_commas()
-    );
-    println!(
-        "Average flames: {}",
-        (average_flames.ceil() as u32).separate_with_commas()
-    );
-    if flametype == "pflame" {
-        if average_flames.ceil() * 0.00912 >= 1000.0 {
-            println!(
-                "Average cost: {:.5}T",
-                (average_flames.ceil() * 0.00000912).separate_with_commas()
-            );
-        } else {
-            println!(
-                "Average cost: {:.5}b",
-                (average_flames.ceil() * 0.00912).separate_with_commas()
-            );
-        }
-    }
-    println!("\n");
-    if chance > 0 {
-        let odds: f32 = 1.0 - ((1.0 - *count.lock().unwrap() as f32 / *trials as f32).powi(chance));
-        println!(
-            "Chance of getting within {} flames: {:.3}% \n",
-            &chance,
-            odds * 100.0
-        );
-    }
-
-    if top > 1 {
-        println!("Top {} flames:", top);
-        let mut number = 1;
-        for flame in flame_collection.lock().unwrap().clone().iter() {
-            println!("#{}: {:?} with score: {:.2} \n", number, flame.0, flame.1);
-            number += 1;
-        }
-    } else {
-        for flame in flame_collection.lock().unwrap().clone().iter() {
-            println!("Best flame:");
-            for el in flame.0.iter() {
-                if el.1 > 0 {
-                    println!("{:?}, {:?}", el.0, el.1);
-                }
-            }
-            println!("\nscore: {:.2} \n", flame.1);
-        }
-    }
-    let elapsed = now.elapsed();
-    println!("time: {:.3?}", elapsed);
-}
+use clap::{arg, command, value_parser, Arg, ArgAction, ArgMatches, Command, ValueEnum};
+use clap_complete::{generate, Shell};
+use flame_rs::confidence::CONFIDENCE;
+use flame_rs::report::{
+    self, Advice, AdviceReport, BestOf, Budget, ChanceWithin, CurrentFlame, DistributionSummary,
+    Improvement, RankedFlame, Results, Settings, Spend, SweepCell, SweepReport, SweepSettings,
+    ThresholdResults, WriteReport,
+};
+use flame_rs::types::{EquipLevelParser, OutputFormat};
+use flame_rs::{
+    score, EquipLevel, FlameCost, FlameSimulator, FlameType, GameTables, Interval, ItemType, Job,
+    JobStat, LevelBracket, Precision, Price, Report, ScoreFormula, ScoreWeights, SimulationResult,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{env, fs::File, io, path::PathBuf, process, time::Instant};
+use thousands::Separable;
+
+fn inner_main() -> io::Result<PathBuf> {
+    let mut exe = env::current_exe()?;
+    exe.set_file_name("flame_values.json");
+    Ok(exe)
+}
+
+/// Finds a score profile in `flame_values.json`, falling back to the built-in stat and job
+/// presets.
+fn load_profile(
+    path: &str,
+    name: &str,
+    weights: &ScoreWeights,
+) -> Result<ScoreFormula, score::ScoreError> {
+    let error = match score::load_profile(path, name) {
+        Ok(Some(formula)) => return Ok(formula),
+        Ok(None) => score::ScoreError::UnknownProfile(name.to_owned()),
+        Err(e) => e,
+    };
+    if let Ok(stat) = JobStat::from_str(name, true) {
+        return Ok(ScoreFormula::preset(stat, weights));
+    }
+    match Job::from_str(name, true) {
+        Ok(job) => Ok(ScoreFormula::preset(job.stat(), weights)),
+        Err(_) => Err(error),
+    }
+}
+
+/// Formats meso in billions, or trillions past 1000b, rounded to four significant digits.
+fn format_cost(cost: f64) -> String {
+    let (amount, unit) = if cost >= 1e12 {
+        (cost / 1e12, "T")
+    } else {
+        (cost / 1e9, "b")
+    };
+    let whole_digits = amount.max(1.0).log10().floor() as usize + 1;
+    let rounded = format!("{:.*}", 4usize.saturating_sub(whole_digits), amount);
+    let (whole, fraction) = rounded.split_once('.').unwrap_or((&rounded, ""));
+    let fraction = fraction.trim_end_matches('0');
+    let whole = whole
+        .parse::<u64>()
+        .map_or(whole.to_owned(), |whole| whole.separate_with_commas());
+    if fraction.is_empty() {
+        format!("{}{}", whole, unit)
+    } else {
+        format!("{}.{}{}", whole, fraction, unit)
+    }
+}
+
+/// Prints the average meso spent, with the NX spent for flames bought with NX.
+fn print_average_cost(cost: Option<FlameCost>, average_flames: f64) {
+    let Some(meso) = report::average_cost(cost, average_flames) else {
+        return;
+    };
+    match cost.map(|cost| cost.price) {
+        Some(Price::Nx(nx)) => println!(
+            "Average cost: {} ({} NX)",
+            format_cost(meso),
+            ((average_flames.ceil() * nx) as u64).separate_with_commas()
+        ),
+        _ => println!("Average cost: {}", format_cost(meso)),
+    }
+}
+
+/// Formats the average flames needed, rounded up, or `-` when no flame reached the target.
+fn format_average_flames(average_flames: Option<f64>) -> String {
+    average_flames.map_or("-".to_owned(), |flames| {
+        (flames.ceil() as u64).separate_with_commas()
+    })
+}
+
+/// Formats the bounds of an interval of flames, rounded up like the average.
+fn format_flames(interval: Interval) -> String {
+    let bound = |flames: f64| {
+        if flames.is_finite() {
+            (flames.ceil() as u64).separate_with_commas()
+        } else {
+            "unbounded".to_owned()
+        }
+    };
+    format!("{} - {}", bound(interval.lower), bound(interval.upper))
+}
+
+/// Prints a report in one of the machine-readable formats, or nothing for `text`.
+fn print_report(format: OutputFormat, report: &impl WriteReport) {
+    let stdout = io::stdout().lock();
+    let written = match format {
+        OutputFormat::Json => report.write_json(stdout),
+        OutputFormat::Ndjson => report.write_ndjson(stdout),
+        OutputFormat::Csv => report.write_csv(stdout),
+        OutputFormat::Text => Ok(()),
+    };
+    if let Err(e) = written {
+        eprintln!("error writing {} output: {}", format, e);
+        process::exit(1);
+    }
+}
+
+/// Results of an exact run with the given odds of a flame reaching the target, in report form.
+fn exact_results(cost: Option<FlameCost>, probability: f64, chance: i32) -> Results {
+    let average_flames = (probability > 0.0).then(|| 1.0 / probability);
+    Results {
+        count: None,
+        probability,
+        average_flames,
+        average_cost: average_flames.and_then(|flames| report::average_cost(cost, flames)),
+        chance_within: (chance > 0).then(|| ChanceWithin {
+            flames: chance,
+            probability: 1.0 - (1.0 - probability).powi(chance),
+            interval: None,
+        }),
+        probability_interval: None,
+        average_flames_interval: None,
+        average_cost_interval: None,
+        low_count: false,
+        spend: Spend::percentiles(cost, probability, None),
+    }
+}
+
+/// Results of a simulation, in report form.
+fn simulation_results(cost: Option<FlameCost>, result: &SimulationResult, chance: i32) -> Results {
+    let average_flames = (result.count > 0).then(|| result.average_flames());
+    let average_flames_interval = result.average_flames_interval();
+    Results {
+        count: Some(result.count),
+        probability: result.probability(),
+        probability_interval: Some(result.probability_interval()),
+        average_flames,
+        average_flames_interval: Some(average_flames_interval),
+        average_cost: average_flames.and_then(|flames| report::average_cost(cost, flames)),
+        average_cost_interval: report::average_cost_interval(cost, average_flames_interval),
+        chance_within: (chance > 0).then(|| ChanceWithin {
+            flames: chance,
+            probability: result.chance_within(chance),
+            interval: Some(result.chance_within_interval(chance)),
+        }),
+        low_count: !result.is_reliable(),
+        spend: Spend::percentiles(
+            cost,
+            result.probability(),
+            Some(result.probability_interval()),
+        ),
+    }
+}
+
+/// Exact odds of reaching the target within every amount of flames.
+fn exact_curve(probability: f64, flames: &[i32]) -> Vec<ChanceWithin> {
+    flames
+        .iter()
+        .map(|flames| ChanceWithin {
+            flames: *flames,
+            probability: 1.0 - (1.0 - probability).powi(*flames),
+            interval: None,
+        })
+        .collect()
+}
+
+/// Simulated odds of reaching the target within every amount of flames.
+fn simulation_curve(result: &SimulationResult, flames: &[i32]) -> Vec<ChanceWithin> {
+    flames
+        .iter()
+        .map(|flames| ChanceWithin {
+            flames: *flames,
+            probability: result.chance_within(*flames),
+            interval: Some(result.chance_within_interval(*flames)),
+        })
+        .collect()
+}
+
+/// Prints the odds of reaching the target within every amount of flames of a curve.
+fn print_chance_curve(curve: &[ChanceWithin]) {
+    let percent = |p: f64| format!("{:.3}%", p * 100.0);
+    println!("Chance of getting within:");
+    print_table(
+        vec![
+            (
+                "Flames".to_owned(),
+                curve
+                    .iter()
+                    .map(|chance| Some(chance.flames.separate_with_commas()))
+                    .collect(),
+            ),
+            (
+                "Odds".to_owned(),
+                curve
+                    .iter()
+                    .map(|chance| Some(percent(chance.probability)))
+                    .collect(),
+            ),
+            (
+                format!("{}% CI", CONFIDENCE * 100.0),
+                curve
+                    .iter()
+                    .map(|chance| {
+                        chance.interval.map(|interval| {
+                            format!("{} - {}", percent(interval.lower), percent(interval.upper))
+                        })
+                    })
+                    .collect(),
+            ),
+        ],
+        curve.len(),
+    );
+}
+
+/// Writes the chance curve to the `--chance-csv` file, if one was given.
+fn write_chance_csv(matches: &ArgMatches, curve: &[ChanceWithin]) {
+    let Some(path) = matches.get_one::<PathBuf>("chance-csv") else {
+        return;
+    };
+    let written = File::create(path)
+        .and_then(|file| report::write_chance_csv(io::BufWriter::new(file), curve));
+    if let Err(e) = written {
+        eprintln!("error writing {}: {}", path.display(), e);
+        process::exit(1);
+    }
+}
+
+/// Exact results for every threshold of a run with several, or none with one.
+fn exact_thresholds(
+    cost: Option<FlameCost>,
+    distribution: &flame_rs::ScoreDistribution,
+    keeps: &[f32],
+    chance: i32,
+) -> Vec<ThresholdResults> {
+    if keeps.len() < 2 {
+        return Vec::new();
+    }
+    keeps
+        .iter()
+        .map(|keep| ThresholdResults {
+            keep: *keep,
+            results: exact_results(
+                cost,
+                distribution.probability_at_least(*keep as f64),
+                chance,
+            ),
+        })
+        .collect()
+}
+
+/// Simulated results for every `--keep` target of a run with several, or none with one.
+///
+/// The lower targets are the first extra thresholds of the run, the highest one is its `keep`.
+fn simulation_thresholds(
+    cost: Option<FlameCost>,
+    result: &SimulationResult,
+    keeps: &[f32],
+    chance: i32,
+) -> Vec<ThresholdResults> {
+    if keeps.len() < 2 {
+        return Vec::new();
+    }
+    let mut thresholds: Vec<ThresholdResults> = result.thresholds[..keeps.len() - 1]
+        .iter()
+        .map(|threshold| ThresholdResults {
+            keep: threshold.keep,
+            results: simulation_results(cost, &result.for_threshold(threshold), chance),
+        })
+        .collect();
+    thresholds.push(ThresholdResults {
+        keep: *keeps.last().unwrap(),
+        results: simulation_results(cost, result, chance),
+    });
+    thresholds
+}
+
+/// Odds of improving on the current flame, with the budget advice when `--budget` is given.
+fn current_flame(
+    matches: &ArgMatches,
+    cost: Option<FlameCost>,
+    current: f32,
+    flames: Option<u32>,
+    beat: Results,
+    improve: Option<Results>,
+    distribution: Option<&flame_rs::ScoreDistribution>,
+) -> CurrentFlame {
+    let keep_old = matches.get_flag("keep-old");
+    let point_value = matches.get_one::<f64>("point-value").copied();
+    let budget = flames.zip(distribution).map(|(flames, distribution)| {
+        Budget::new(
+            distribution,
+            current,
+            flames,
+            keep_old,
+            cost,
+            point_value,
+            beat.probability,
+        )
+    });
+    CurrentFlame {
+        score: current,
+        keep_old,
+        beat,
+        improve: matches
+            .get_one::<f32>("improve")
+            .zip(improve)
+            .map(|(points, results)| Improvement {
+                points: *points,
+                results,
+            }),
+        budget,
+    }
+}
+
+fn exact_current(
+    matches: &ArgMatches,
+    cost: Option<FlameCost>,
+    distribution: &flame_rs::ScoreDistribution,
+    current: f32,
+    flames: Option<u32>,
+    chance: i32,
+) -> CurrentFlame {
+    let beat = exact_results(cost, distribution.probability_above(current as f64), chance);
+    let improve = matches.get_one::<f32>("improve").map(|points| {
+        exact_results(
+            cost,
+            distribution.probability_at_least((current + points) as f64),
+            chance,
+        )
+    });
+    current_flame(
+        matches,
+        cost,
+        current,
+        flames,
+        beat,
+        improve,
+        Some(distribution),
+    )
+}
+
+/// The current flame's thresholds follow the lower `--keep` targets in the run's thresholds.
+fn simulation_current(
+    matches: &ArgMatches,
+    cost: Option<FlameCost>,
+    result: &SimulationResult,
+    keeps: usize,
+    current: f32,
+    flames: Option<u32>,
+    chance: i32,
+) -> CurrentFlame {
+    let counted = &result.thresholds[keeps - 1..];
+    let beat = simulation_results(cost, &result.for_threshold(&counted[0]), chance);
+    let improve = counted
+        .get(1)
+        .map(|threshold| simulation_results(cost, &result.for_threshold(threshold), chance));
+    current_flame(
+        matches,
+        cost,
+        current,
+        flames,
+        beat,
+        improve,
+        result.distribution.as_ref(),
+    )
+}
+
+/// Flames left to list stop thresholds for: every one up to 10, then a few per power of ten,
+/// and the whole budget.
+fn budget_checkpoints(flames: u32) -> Vec<u32> {
+    let mut checkpoints: Vec<u32> = (1..=flames.min(10)).collect();
+    let mut scale = 10;
+    while scale < flames {
+        checkpoints.extend(
+            [2, 3, 5, 10]
+                .iter()
+                .map(|step| step * scale)
+                .filter(|checkpoint| *checkpoint < flames),
+        );
+        scale *= 10;
+    }
+    if flames > 10 {
+        checkpoints.push(flames);
+    }
+    checkpoints.dedup();
+    checkpoints
+}
+
+fn print_current(current: &CurrentFlame) {
+    let results = |description: String, results: &Results| {
+        match results.probability_interval {
+            Some(interval) => println!(
+                "Chance a flame {}: {:.4}% ({}% CI {:.4}% - {:.4}%)",
+                description,
+                results.probability * 100.0,
+                CONFIDENCE * 100.0,
+                interval.lower * 100.0,
+                interval.upper * 100.0
+            ),
+            None => println!(
+                "Chance a flame {}: {:.6}%",
+                description,
+                results.probability * 100.0
+            ),
+        }
+        match results.average_flames_interval {
+            Some(interval) => println!(
+                "Average flames: {} ({}% CI {})",
+                format_average_flames(results.average_flames),
+                CONFIDENCE * 100.0,
+                format_flames(interval)
+            ),
+            None => println!(
+                "Average flames: {}",
+                format_average_flames(results.average_flames)
+            ),
+        }
+        if let Some(cost) = results.average_cost {
+            println!("Average cost: {}", format_cost(cost));
+        }
+        println!();
+    };
+
+    println!("Current flame: {}", current.score);
+    results(format!("beats {}", current.score), &current.beat);
+    if let Some(improve) = &current.improve {
+        results(
+            format!(
+                "scores at least {} (+{})",
+                current.score + improve.points,
+                improve.points
+            ),
+            &improve.results,
+        );
+    }
+
+    let Some(budget) = &current.budget else {
+        return;
+    };
+    println!(
+        "Budget: {} flames ({})",
+        budget.flames.separate_with_commas(),
+        if current.keep_old {
+            "the better flame is kept"
+        } else {
+            "every flame replaces the last"
+        }
+    );
+    println!(
+        "Chance to beat {} within the budget: {:.3}%",
+        current.score,
+        budget.chance_to_beat * 100.0
+    );
+    let verdict = if budget.keep_flaming {
+        "Keep flaming"
+    } else {
+        "Stop"
+    };
+    match budget.stop_thresholds.last() {
+        Some(threshold) if current.keep_old && budget.keep_flaming => println!(
+            "Keep flaming: stop once the item scores {:.2} or more",
+            threshold.score
+        ),
+        Some(threshold) if current.keep_old => println!(
+            "Stop: flaming on stops paying off from a score of {:.2}",
+            threshold.score
+        ),
+        Some(threshold) => println!(
+            "{}: with {} flames left, stop at a score of {:.2} or more",
+            verdict,
+            threshold.flames_left.separate_with_commas(),
+            threshold.score
+        ),
+        None => println!("{}", verdict),
+    }
+    match budget.expected_cost.filter(|_| budget.keep_flaming) {
+        Some(cost) => println!(
+            "Expected flames used: {:.1} ({})",
+            budget.expected_flames,
+            format_cost(cost)
+        ),
+        None if budget.keep_flaming => {
+            println!("Expected flames used: {:.1}", budget.expected_flames)
+        }
+        None => {}
+    }
+    println!("Expected final score: {:.2} \n", budget.expected_score);
+    if !current.keep_old && !budget.stop_thresholds.is_empty() {
+        println!("Stop thresholds by flames left:");
+        for flames_left in budget_checkpoints(budget.flames) {
+            let threshold = budget.stop_thresholds[flames_left as usize - 1];
+            println!(
+                "{:>10}: {:.2}",
+                flames_left.separate_with_commas(),
+                threshold.score
+            );
+        }
+        println!();
+    }
+}
+
+/// Percentile of luck the budget line is given for.
+const BUDGET_PERCENTILE: f64 = 90.0;
+
+/// Prints the flames and meso spent to reach the target at every percentile of luck.
+fn print_spend(results: &Results) {
+    if results.spend.is_empty() {
+        return;
+    }
+    let spend = &results.spend;
+    let flames = |flames: f64| {
+        if flames.is_finite() {
+            (flames as u64).separate_with_commas()
+        } else {
+            "unbounded".to_owned()
+        }
+    };
+    let cost = |cost: f64| {
+        if cost.is_finite() {
+            format_cost(cost)
+        } else {
+            "unbounded".to_owned()
+        }
+    };
+    let bounds = |interval: Option<Interval>, format: &dyn Fn(f64) -> String| {
+        interval.map(|interval| format!("{} - {}", format(interval.lower), format(interval.upper)))
+    };
+
+    println!("Flames needed in a share of attempts:");
+    print_table(
+        vec![
+            (
+                "Attempts".to_owned(),
+                spend
+                    .iter()
+                    .map(|spend| Some(format!("{}%", spend.percentile)))
+                    .collect(),
+            ),
+            (
+                "Flames".to_owned(),
+                spend
+                    .iter()
+                    .map(|spend| Some(spend.flames.separate_with_commas()))
+                    .collect(),
+            ),
+            (
+                format!("{}% CI", CONFIDENCE * 100.0),
+                spend
+                    .iter()
+                    .map(|spend| bounds(spend.flames_interval, &flames))
+                    .collect(),
+            ),
+            (
+                "Cost".to_owned(),
+                spend
+                    .iter()
+                    .map(|spend| spend.cost.map(format_cost))
+                    .collect(),
+            ),
+            (
+                format!("{}% CI", CONFIDENCE * 100.0),
+                spend
+                    .iter()
+                    .map(|spend| bounds(spend.cost_interval, &cost))
+                    .collect(),
+            ),
+        ],
+        spend.len(),
+    );
+    if let Some(budget) = spend
+        .iter()
+        .find(|spend| spend.percentile == BUDGET_PERCENTILE)
+    {
+        match budget.cost {
+            Some(cost) => println!(
+                "Budget for {}% confidence: {} flames ({}) \n",
+                BUDGET_PERCENTILE,
+                budget.flames.separate_with_commas(),
+                format_cost(cost)
+            ),
+            None => println!(
+                "Budget for {}% confidence: {} flames \n",
+                BUDGET_PERCENTILE,
+                budget.flames.separate_with_commas()
+            ),
+        }
+    }
+}
+
+/// Prints the exact outcome, with the odds of every possible score when `per_score` is set.
+fn print_exact(
+    cost: Option<FlameCost>,
+    distribution: &flame_rs::ScoreDistribution,
+    keep: f32,
+    chance: i32,
+    thresholds: &[ThresholdResults],
+    per_score: bool,
+) {
+    let keep = keep as f64;
+    let average_flames =
+        (distribution.probability_at_least(keep) > 0.0).then(|| distribution.average_flames(keep));
+
+    println!("Results (exact):");
+    if thresholds.is_empty() {
+        println!(
+            "Chance of a flame over {} flamescore: {:.6}% \n",
+            keep,
+            distribution.probability_at_least(keep) * 100.0
+        );
+        println!("Average flames: {}", format_average_flames(average_flames));
+        if let Some(average_flames) = average_flames {
+            print_average_cost(cost, average_flames);
+        }
+        println!("\n");
+        if chance > 0 {
+            println!(
+                "Chance of getting within {} flames: {:.3}% \n",
+                &chance,
+                distribution.chance_within(keep, chance) * 100.0
+            );
+        }
+        print_spend(&exact_results(
+            cost,
+            distribution.probability_at_least(keep),
+            chance,
+        ));
+    } else {
+        print_thresholds(thresholds);
+    }
+
+    println!("Average flamescore: {:.2} \n", distribution.mean());
+    if !per_score {
+        return;
+    }
+    println!("Odds of every score:");
+    let mut at_least = 1.0;
+    for (score, p) in distribution.pmf.iter() {
+        println!(
+            "{:>8.2}: {:>10.6}% (score >= {:.2}: {:.6}%)",
+            score,
+            p * 100.0,
+            score,
+            at_least * 100.0
+        );
+        at_least -= p;
+    }
+}
+
+/// Prints the simulated outcome for a single threshold.
+fn print_simulation(
+    cost: Option<FlameCost>,
+    result: &SimulationResult,
+    keep: f32,
+    chance: i32,
+    importance: bool,
+) {
+    let average_flames = (result.count > 0).then(|| result.average_flames());
+    let probability = result.probability_interval();
+    println!(
+        "Flames over {} flamescore: {}/{}{} ({:.4}%, {}% CI {:.4}% - {:.4}%) \n",
+        keep,
+        result.count.separate_with_commas(),
+        result.trials.separate_with_commas(),
+        if importance { " biased rolls" } else { "" },
+        result.probability() * 100.0,
+        CONFIDENCE * 100.0,
+        probability.lower * 100.0,
+        probability.upper * 100.0
+    );
+    println!(
+        "Average flames: {} ({}% CI {})",
+        format_average_flames(average_flames),
+        CONFIDENCE * 100.0,
+        format_flames(result.average_flames_interval())
+    );
+    if let Some(average_flames) = average_flames {
+        print_average_cost(cost, average_flames);
+    }
+    if let Some(cost) = report::average_cost_interval(cost, result.average_flames_interval()) {
+        let bound = |cost: f64| {
+            if cost.is_finite() {
+                format_cost(cost)
+            } else {
+                "unbounded".to_owned()
+            }
+        };
+        println!(
+            "Average cost {}% CI: {} - {}",
+            CONFIDENCE * 100.0,
+            bound(cost.lower),
+            bound(cost.upper)
+        );
+    }
+    println!("\n");
+    if chance > 0 {
+        let interval = result.chance_within_interval(chance);
+        println!(
+            "Chance of getting within {} flames: {:.3}% ({}% CI {:.3}% - {:.3}%) \n",
+            &chance,
+            result.chance_within(chance) * 100.0,
+            CONFIDENCE * 100.0,
+            interval.lower * 100.0,
+            interval.upper * 100.0
+        );
+    }
+    print_spend(&simulation_results(cost, result, chance));
+    if !result.is_reliable() {
+        println!(
+            "Warning: only {} flames reached the target, too few for reliable estimates. \
+             Increase --trials or use --exact. \n",
+            result.count
+        );
+    }
+}
+
+/// Prints right-aligned columns of `rows` cells, leaving out columns with no cells and showing
+/// missing cells as `-`.
+fn print_table(columns: Vec<(String, Vec<Option<String>>)>, rows: usize) {
+    let columns: Vec<(String, Vec<String>, usize)> = columns
+        .into_iter()
+        .filter(|(_, cells)| cells.iter().any(Option::is_some))
+        .map(|(header, cells)| {
+            let cells: Vec<String> = cells
+                .into_iter()
+                .map(|cell| cell.unwrap_or_else(|| "-".to_owned()))
+                .collect();
+            let width = cells.iter().map(String::len).fold(header.len(), usize::max);
+            (header, cells, width)
+        })
+        .collect();
+    let header: Vec<String> = columns
+        .iter()
+        .map(|(header, _, width)| format!("{:>width$}", header, width = width))
+        .collect();
+    println!("{}", header.join(" | "));
+    for row in 0..rows {
+        let cells: Vec<String> = columns
+            .iter()
+            .map(|(_, cells, width)| format!("{:>width$}", cells[row], width = width))
+            .collect();
+        println!("{}", cells.join(" | "));
+    }
+    println!();
+}
+
+/// Prints a table of the outcome for every threshold of a run with several.
+///
+/// Columns that are empty for every threshold, like the intervals of exact results, are left
+/// out.
+fn print_thresholds(thresholds: &[ThresholdResults]) {
+    let percent = |p: f64| format!("{:.4}%", p * 100.0);
+    let flames = |flames: f64| {
+        if flames.is_finite() {
+            (flames.ceil() as u64).separate_with_commas()
+        } else {
+            "unbounded".to_owned()
+        }
+    };
+    let bounds = |interval: Option<Interval>, format: &dyn Fn(f64) -> String| {
+        interval.map(|interval| format!("{} - {}", format(interval.lower), format(interval.upper)))
+    };
+
+    let chance = thresholds
+        .iter()
+        .find_map(|threshold| threshold.results.chance_within.as_ref())
+        .map(|chance| chance.flames);
+    let columns: Vec<(String, Vec<Option<String>>)> = vec![
+        (
+            "Target".to_owned(),
+            thresholds
+                .iter()
+                .map(|threshold| Some(format!("{:.2}", threshold.keep)))
+                .collect(),
+        ),
+        (
+            "Flames".to_owned(),
+            thresholds
+                .iter()
+                .map(|threshold| {
+                    threshold
+                        .results
+                        .count
+                        .map(|count| count.separate_with_commas())
+                })
+                .collect(),
+        ),
+        (
+            "Odds".to_owned(),
+            thresholds
+                .iter()
+                .map(|threshold| Some(percent(threshold.results.probability)))
+                .collect(),
+        ),
+        (
+            format!("{}% CI", CONFIDENCE * 100.0),
+            thresholds
+                .iter()
+                .map(|threshold| bounds(threshold.results.probability_interval, &percent))
+                .collect(),
+        ),
+        (
+            "Average flames".to_owned(),
+            thresholds
+                .iter()
+                .map(|threshold| {
+                    Some(
+                        threshold
+                            .results
+                            .average_flames
+                            .map_or("-".to_owned(), flames),
+                    )
+                })
+                .collect(),
+        ),
+        (
+            format!("{}% CI", CONFIDENCE * 100.0),
+            thresholds
+                .iter()
+                .map(|threshold| bounds(threshold.results.average_flames_interval, &flames))
+                .collect(),
+        ),
+        (
+            "Average cost".to_owned(),
+            thresholds
+                .iter()
+                .map(|threshold| threshold.results.average_cost.map(format_cost))
+                .collect(),
+        ),
+        (
+            format!("{}% budget", BUDGET_PERCENTILE),
+            thresholds
+                .iter()
+                .map(|threshold| {
+                    let budget = threshold
+                        .results
+                        .spend
+                        .iter()
+                        .find(|spend| spend.percentile == BUDGET_PERCENTILE)?;
+                    Some(match budget.cost {
+                        Some(cost) => format!(
+                            "{} ({})",
+                            budget.flames.separate_with_commas(),
+                            format_cost(cost)
+                        ),
+                        None => budget.flames.separate_with_commas(),
+                    })
+                })
+                .collect(),
+        ),
+        (
+            format!("Within {} flames", chance.unwrap_or(0)),
+            thresholds
+                .iter()
+                .map(|threshold| {
+                    let chance = threshold.results.chance_within.as_ref()?;
+                    Some(percent(chance.probability))
+                })
+                .collect(),
+        ),
+    ];
+
+    print_table(columns, thresholds.len());
+    if thresholds
+        .iter()
+        .any(|threshold| threshold.results.low_count)
+    {
+        println!(
+            "Warning: fewer than {} flames reached some targets, too few for reliable estimates. \
+             Increase --trials or use --exact. \n",
+            flame_rs::confidence::MIN_RELIABLE_COUNT
+        );
+    }
+}
+
+/// Parses one or more flamescore targets: `100`, a list like `100,110,120`, or a range like
+/// `100..200:10` including both ends. The targets are sorted and deduplicated.
+fn parse_thresholds(value: &str) -> Result<Vec<f32>, String> {
+    let number = |number: &str| {
+        number
+            .trim()
+            .parse::<f32>()
+            .ok()
+            .filter(|number| number.is_finite())
+            .ok_or_else(|| format!("'{}' isn't a flamescore", number))
+    };
+    let mut thresholds = Vec::new();
+    for part in value.split(',') {
+        match part.split_once("..") {
+            Some((start, rest)) => {
+                let (end, step) = rest
+                    .split_once(':')
+                    .ok_or_else(|| format!("'{}' needs a step, e.g. 100..200:10", part))?;
+                let (start, end, step) = (number(start)?, number(end)?, number(step)?);
+                if step <= 0.0 || end < start {
+                    return Err(format!("'{}' isn't an ascending range", part));
+                }
+                let steps = ((end - start) / step + 1e-3).floor() as usize;
+                if thresholds.len().saturating_add(steps) >= MAX_THRESHOLDS {
+                    return Err(format!("at most {} targets are allowed", MAX_THRESHOLDS));
+                }
+                thresholds.extend((0..=steps).map(|i| start + i as f32 * step));
+            }
+            None => thresholds.push(number(part)?),
+        }
+    }
+    if thresholds.len() > MAX_THRESHOLDS {
+        return Err(format!("at most {} targets are allowed", MAX_THRESHOLDS));
+    }
+    thresholds.sort_by(f32::total_cmp);
+    thresholds.dedup();
+    Ok(thresholds)
+}
+
+/// Most targets `--keep` accepts.
+const MAX_THRESHOLDS: usize = 1000;
+
+/// Parses an amount of flames for `--chance`, or several separated by commas.
+fn parse_checkpoints(value: &str) -> Result<Vec<i32>, String> {
+    let mut checkpoints = Vec::new();
+    for part in value.split(',') {
+        let flames = part
+            .trim()
+            .parse::<i32>()
+            .ok()
+            .filter(|flames| *flames > 0)
+            .ok_or_else(|| format!("'{}' isn't an amount of flames above 0", part))?;
+        checkpoints.push(flames);
+    }
+    if checkpoints.len() > MAX_THRESHOLDS {
+        return Err(format!(
+            "at most {} checkpoints are allowed",
+            MAX_THRESHOLDS
+        ));
+    }
+    checkpoints.sort_unstable();
+    checkpoints.dedup();
+    Ok(checkpoints)
+}
+
+/// Most flames `--curve` lists the odds for.
+const MAX_CURVE_FLAMES: i32 = 100_000;
+
+/// Flames to spend from `--budget`, or as many as `--budget-meso` buys.
+fn budget_flames(
+    matches: &ArgMatches,
+    flametype: FlameType,
+    cost: Option<FlameCost>,
+) -> Option<u32> {
+    if let Some(flames) = matches.get_one::<u32>("budget") {
+        return Some(*flames);
+    }
+    let meso = matches.get_one::<f64>("budget-meso")?;
+    let Some(cost) = cost else {
+        eprintln!(
+            "error: {} has no price, give --budget in flames instead",
+            flametype
+        );
+        process::exit(1);
+    };
+    let flames = (meso / cost.meso).floor();
+    if flames < 1.0 {
+        eprintln!(
+            "error: {} doesn't buy a single {} ({} each)",
+            format_cost(*meso),
+            flametype,
+            format_cost(cost.meso)
+        );
+        process::exit(1);
+    }
+    if flames > MAX_BUDGET as f64 {
+        eprintln!(
+            "error: {} buys {} flames, more than the {} a budget can hold",
+            format_cost(*meso),
+            (flames as u64).separate_with_commas(),
+            MAX_BUDGET.separate_with_commas()
+        );
+        process::exit(1);
+    }
+    Some(flames as u32)
+}
+
+/// Distribution of the best score out of a budget of flames.
+fn best_of(
+    distribution: &flame_rs::ScoreDistribution,
+    flames: u32,
+    cost: Option<FlameCost>,
+) -> BestOf {
+    BestOf {
+        flames,
+        cost: cost.map(|cost| flames as f64 * cost.meso),
+        distribution: DistributionSummary::with_percentiles(
+            &distribution.best_of(flames),
+            &report::BEST_OF_PERCENTILES,
+        ),
+    }
+}
+
+fn print_best_of(best_of: &BestOf) {
+    let title = match best_of.cost {
+        Some(cost) => format!(
+            "Best flamescore out of {} flames ({}):",
+            best_of.flames.separate_with_commas(),
+            format_cost(cost)
+        ),
+        None => format!(
+            "Best flamescore out of {} flames:",
+            best_of.flames.separate_with_commas()
+        ),
+    };
+    println!("{}", title);
+    println!("Expected best score: {:.2}", best_of.distribution.mean);
+    print_summary(&best_of.distribution);
+}
+
+/// Most flames `--budget` and `--budget-meso` accept.
+const MAX_BUDGET: u32 = 1_000_000;
+
+/// Width of the longest histogram bar.
+const HISTOGRAM_WIDTH: f64 = 50.0;
+
+fn print_distribution(title: &str, summary: &DistributionSummary) {
+    println!("{}", title);
+    print_summary(summary);
+}
+
+/// Prints the moments, percentiles, histogram and odds of a distribution, under its title.
+fn print_summary(summary: &DistributionSummary) {
+    println!("Mean: {:.2}, Std dev: {:.2}", summary.mean, summary.std_dev);
+    let percentiles: Vec<String> = summary
+        .percentiles
+        .iter()
+        .map(|percentile| format!("p{}: {:.2}", percentile.percentile, percentile.score))
+        .collect();
+    println!("{} \n", percentiles.join(", "));
+
+    let tallest = summary
+        .histogram
+        .iter()
+        .map(|bin| bin.probability)
+        .fold(0.0, f64::max);
+    for bin in summary.histogram.iter() {
+        let width = if tallest > 0.0 {
+            (bin.probability / tallest * HISTOGRAM_WIDTH).round() as usize
+        } else {
+            0
+        };
+        println!(
+            "{:>8.2} - {:>8.2} | {:<50} {:>10.6}%",
+            bin.lower,
+            bin.upper,
+            "#".repeat(width),
+            bin.probability * 100.0
+        );
+    }
+    println!();
+
+    for at_least in summary.at_least.iter() {
+        println!(
+            "score >= {:>8.2}: {:>10.6}%",
+            at_least.score,
+            at_least.probability * 100.0
+        );
+    }
+    println!();
+}
+
+/// Parses an amount of meso such as `2b`, `500m`, `1.5t` or `250,000,000`.
+fn parse_meso(value: &str) -> Result<f64, String> {
+    let digits = value.replace([',', '_'], "").to_lowercase();
+    let (number, scale) = match digits.char_indices().last() {
+        Some((i, 'k')) => (&digits[..i], 1e3),
+        Some((i, 'm')) => (&digits[..i], 1e6),
+        Some((i, 'b')) => (&digits[..i], 1e9),
+        Some((i, 't')) => (&digits[..i], 1e12),
+        _ => (digits.as_str(), 1.0),
+    };
+    let meso: f64 = number
+        .parse()
+        .map_err(|_| format!("'{}' isn't an amount of meso", value))?;
+    if meso > 0.0 && meso.is_finite() {
+        Ok(meso * scale)
+    } else {
+        Err("the amount of meso must be above 0".to_owned())
+    }
+}
+
+/// Parses a percentage such as `2%` or `2` into a fraction.
+fn parse_percent(value: &str) -> Result<f64, String> {
+    let percent: f64 = value
+        .trim_end_matches('%')
+        .parse()
+        .map_err(|_| format!("'{}' isn't a percentage", value))?;
+    if percent > 0.0 && percent.is_finite() {
+        Ok(percent / 100.0)
+    } else {
+        Err("the percentage must be above 0".to_owned())
+    }
+}
+
+fn cli() -> Command {
+    command!()
+    .arg(
+        arg!(
+            -t --trials <TRIALS> "Amount of times to run the simulator"
+        )
+        .value_parser(value_parser!(u64))
+        .default_value("100000")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --precision <PERCENT> "Runs until the odds are known to within this percentage of themselves (95% confidence), instead of a fixed amount of trials"
+        )
+        .value_parser(parse_percent)
+        .required(false)
+        .conflicts_with("trials")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --"max-trials" <TRIALS> "Most trials to run with --precision"
+        )
+        .value_parser(value_parser!(u64))
+        .default_value("100000000")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            -s --stat <STAT> "Stat to roll for"
+        )
+        .value_parser(value_parser!(JobStat))
+        .default_value("str")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            -j --job <JOB> "Job preset, used instead of --stat"
+        )
+        .value_parser(value_parser!(Job))
+        .required(false)
+        .global(true),
+    )
+    .arg(
+        arg!(
+            -p --profile <NAME> "Score profile from flame_values.json, or a stat or job preset, used instead of --stat"
+        )
+        .value_parser(value_parser!(String))
+        .required(false)
+        .conflicts_with("job")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            -l --level <LEVEL> "Equip level (100-300) or level bracket"
+        )
+        .value_parser(EquipLevelParser)
+        .default_value("140-149"),
+    )
+    .arg(
+        arg!(
+            -k --keep <THRESHOLD> "Minimum flamescore target, or several like 100,110,120 or 100..200:10"
+        )
+        .value_parser(parse_thresholds)
+        .default_value("100")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            -f --flametype <FLAMETYPE> "Type of flame used"
+        )
+        .value_parser(value_parser!(FlameType))
+        .default_value("pflame"),
+    )
+    .arg(
+        arg!(
+            --top <NUMBER> "Displays the top scoring flames (max 1000)"
+        )
+        .value_parser(value_parser!(usize))
+        .required(false)
+    )
+    .arg(
+        arg!(
+            -c --chance <NUMBER> "Calculates the odds of getting target flame within the specified amount of flames, or at several like 10,50,100"
+        )
+        .value_parser(parse_checkpoints)
+        .required(false)
+    )
+    .arg(
+        Arg::new("curve")
+            .long("curve")
+            .help("With --chance, prints the odds within every amount of flames up to the largest")
+            .action(ArgAction::SetTrue)
+            .requires("chance"),
+    )
+    .arg(
+        arg!(
+            --"chance-csv" <PATH> "With --chance, also writes the odds at every checkpoint to a CSV file for plotting"
+        )
+        .value_parser(value_parser!(PathBuf))
+        .required(false)
+        .requires("chance")
+    )
+    .arg(
+        arg!(
+            --item <ITEM> "Type of item flamed"
+        )
+        .value_parser(["armor", "weapon"])
+        .default_value("armor")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --"base-att" <N> "Base attack of the weapon, required for weapon flames"
+        )
+        .value_parser(value_parser!(u16))
+        .required_if_eq("item", "weapon")
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --seed <SEED> "Seed for the random rolls, to reproduce a previous run"
+        )
+        .value_parser(value_parser!(u64))
+        .required(false)
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --current <SCORE> "Flamescore of the item's current flame, to compute the odds of improving on it"
+        )
+        .value_parser(value_parser!(f32))
+        .required(false)
+    )
+    .arg(
+        arg!(
+            --improve <POINTS> "With --current, also computes the odds of improving by at least this many points"
+        )
+        .value_parser(value_parser!(f32))
+        .required(false)
+        .requires("current")
+    )
+    .arg(
+        arg!(
+            --budget <FLAMES> "Flames to spend, to compute the best score to expect from them (and when to stop flaming with --current)"
+        )
+        .value_parser(value_parser!(u32).range(1..=MAX_BUDGET as i64))
+        .required(false)
+    )
+    .arg(
+        arg!(
+            --"budget-meso" <MESO> "Meso to spend instead of --budget, like 2b or 500m"
+        )
+        .value_parser(parse_meso)
+        .required(false)
+        .conflicts_with("budget")
+    )
+    .arg(
+        arg!(
+            --"point-value" <MESO> "With --current and a budget, meso one point of flamescore is worth, to only flame on while it pays for the flames"
+        )
+        .value_parser(parse_meso)
+        .required(false)
+        .requires("current")
+    )
+    .arg(
+        Arg::new("keep-old")
+            .long("keep-old")
+            .help("With --current, new flames only replace the current one if they score higher")
+            .action(ArgAction::SetTrue)
+            .requires("current"),
+    )
+    .arg(
+        Arg::new("noboss")
+            .short('n')
+            .long("noboss")
+            .help("Simulate non-boss flames")
+            .action(ArgAction::SetTrue)
+        .global(true),
+    )
+    .arg(
+        Arg::new("importance")
+            .long("importance")
+            .help("Bias rolls toward the --keep target and reweight them, for rare targets")
+            .action(ArgAction::SetTrue)
+        .global(true),
+    )
+    .arg(
+        Arg::new("distribution")
+            .long("distribution")
+            .help("Prints the flamescore distribution: percentiles, histogram and odds of reaching each score")
+            .action(ArgAction::SetTrue),
+    )
+    .arg(
+        Arg::new("exact")
+            .long("exact")
+            .help("Compute the exact flamescore distribution instead of simulating")
+            .action(ArgAction::SetTrue)
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --tables <PATH> "Game tables file to use instead of the bundled flame_tables.toml"
+        )
+        .value_parser(value_parser!(PathBuf))
+        .required(false)
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --"meso-per-nx" <RATE> "Meso per NX to convert NX flame prices with, instead of the rate in the tables"
+        )
+        .value_parser(value_parser!(f64))
+        .required(false)
+        .global(true),
+    )
+    .arg(
+        arg!(
+            --format <FORMAT> "Output format, see the README for the json, csv and ndjson layouts"
+        )
+        .value_parser(value_parser!(OutputFormat))
+        .default_value("text")
+        .global(true),
+    )
+    .subcommand(
+        Command::new("sweep")
+            .about("Compares every combination of flame types and level brackets")
+            .arg(
+                arg!(
+                    -f --flametype <FLAMETYPES> "Flame types to compare, comma separated (default: all)"
+                )
+                .value_parser(value_parser!(FlameType))
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .required(false),
+            )
+            .arg(
+                arg!(
+                    -l --level <LEVELS> "Equip levels or level brackets to compare, comma separated (default: all)"
+                )
+                .value_parser(EquipLevelParser)
+                .value_delimiter(',')
+                .action(ArgAction::Append)
+                .required(false),
+            ),
+    )
+    .subcommand(
+        Command::new("advise")
+            .about("Ranks every flame type by the meso it takes to reach the target")
+            .arg(
+                arg!(
+                    -l --level <LEVEL> "Equip level (100-300) or level bracket"
+                )
+                .value_parser(EquipLevelParser)
+                .default_value("140-149"),
+            ),
+    )
+    .arg(
+        arg!(
+            --completions <SHELL> "Prints a shell completion script and exits"
+        )
+        .value_parser(value_parser!(Shell))
+        .required(false)
+    )
+}
+
+/// Precision target of an adaptive run, if `--precision` is given.
+fn precision(matches: &ArgMatches) -> Option<Precision> {
+    matches
+        .get_one::<f64>("precision")
+        .map(|relative| Precision {
+            relative: *relative,
+            max_trials: *matches.get_one::<u64>("max-trials").unwrap(),
+        })
+}
+
+fn item_type(matches: &ArgMatches) -> ItemType {
+    match matches.get_one::<String>("item").unwrap().as_str() {
+        "weapon" => ItemType::Weapon {
+            base_att: *matches.get_one::<u16>("base-att").unwrap(),
+        },
+        _ => ItemType::Armor,
+    }
+}
+
+/// Stat archetype flames are scored for, `None` for score profiles.
+fn scored_stat(matches: &ArgMatches) -> Option<JobStat> {
+    match (
+        matches.get_one::<Job>("job"),
+        matches.get_one::<String>("profile"),
+    ) {
+        (Some(job), _) => Some(job.stat()),
+        (_, Some(_)) => None,
+        _ => Some(*matches.get_one::<JobStat>("stat").unwrap()),
+    }
+}
+
+/// Score formula picked with `--job`, `--profile` or `--stat`, and a description of the pick.
+fn scoring(matches: &ArgMatches, dir: &str, weights: &ScoreWeights) -> (String, ScoreFormula) {
+    let stat = *matches.get_one::<JobStat>("stat").unwrap();
+    match (
+        matches.get_one::<Job>("job"),
+        matches.get_one::<String>("profile"),
+    ) {
+        (Some(job), _) => (
+            format!("Job: {} ({})", job, job.stat()),
+            ScoreFormula::preset(job.stat(), weights),
+        ),
+        (_, Some(name)) => (
+            format!("Profile: {}", name),
+            load_profile(dir, name, weights).unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }),
+        ),
+        _ => (
+            format!("Stat: {}", stat),
+            ScoreFormula::preset(stat, weights),
+        ),
+    }
+}
+
+/// Distinct values given to a list argument, in the order given, or every value if none were.
+fn selected<T: Copy + PartialEq + Send + Sync + 'static>(
+    matches: &ArgMatches,
+    id: &str,
+    all: &[T],
+) -> Vec<T> {
+    let mut values: Vec<T> = Vec::new();
+    for value in matches.get_many::<T>(id).into_iter().flatten() {
+        if !values.contains(value) {
+            values.push(*value);
+        }
+    }
+    if values.is_empty() {
+        all.to_vec()
+    } else {
+        values
+    }
+}
+
+/// Seed of the random rolls, from `--seed` or picked at random.
+fn seed(matches: &ArgMatches) -> u64 {
+    match matches.get_one::<u64>("seed") {
+        Some(seed) => *seed,
+        None => rand::random(),
+    }
+}
+
+/// Prints one value of every sweep cell for a target, with a row per level bracket and a column
+/// per flame type.
+fn print_matrix<F>(title: &str, flametypes: &[FlameType], levels: &[EquipLevel], cell: F)
+where
+    F: Fn(usize, usize) -> String,
+{
+    let width = flametypes
+        .iter()
+        .map(|flametype| flametype.to_string().len())
+        .chain(
+            (0..flametypes.len())
+                .flat_map(|f| (0..levels.len()).map(move |l| (f, l)))
+                .map(|(f, l)| cell(f, l).len()),
+        )
+        .max()
+        .unwrap_or(0);
+    let label = levels
+        .iter()
+        .map(|level| level.to_string().len())
+        .fold(9, usize::max);
+    let mut header = format!("{:>label$}", title, label = label);
+    for flametype in flametypes {
+        header += &format!(" | {:>width$}", flametype.to_string(), width = width);
+    }
+    println!("{}", header);
+    for (l, level) in levels.iter().enumerate() {
+        let mut row = format!("{:>label$}", level.to_string(), label = label);
+        for f in 0..flametypes.len() {
+            row += &format!(" | {:>width$}", cell(f, l), width = width);
+        }
+        println!("{}", row);
+    }
+    println!();
+}
+
+/// Outcome of every combination of the flame types, level brackets and `--keep` targets, with
+/// the same seed, in that nesting order.
+fn sweep_cells(
+    matches: &ArgMatches,
+    formula: &ScoreFormula,
+    flametypes: &[FlameType],
+    levels: &[EquipLevel],
+    tables: &GameTables,
+    seed: u64,
+) -> Vec<SweepCell> {
+    let keeps = matches.get_one::<Vec<f32>>("keep").unwrap();
+    let keep = *keeps.last().unwrap();
+    let precision = precision(matches);
+    let trials = *matches.get_one::<u64>("trials").unwrap();
+    let noboss = matches.get_flag("noboss");
+    let item_type = item_type(matches);
+    let importance = matches.get_flag("importance");
+    let exact = matches.get_flag("exact");
+    let format = *matches.get_one::<OutputFormat>("format").unwrap();
+
+    let bar = match format {
+        OutputFormat::Text => ProgressBar::new((flametypes.len() * levels.len()) as u64),
+        _ => ProgressBar::hidden(),
+    };
+    bar.set_style(
+        ProgressStyle::with_template("{wide_bar} {pos}/{len} {msg}")
+            .expect("progress template is valid"),
+    );
+    let mut cells = Vec::new();
+    for flametype in flametypes {
+        for level in levels {
+            bar.set_message(format!("{} {}", flametype, level));
+            let cost = tables.flame_cost(*flametype);
+            let mut simulator = FlameSimulator::new(
+                formula.clone(),
+                *level,
+                *flametype,
+                noboss,
+                item_type,
+                tables,
+            )
+            .unwrap_or_else(|e| {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            });
+
+            let cell = |trials: Option<u64>, threshold: ThresholdResults| SweepCell {
+                flametype: flametype.to_string(),
+                level: level.to_string(),
+                trials,
+                keep: threshold.keep,
+                flame_cost: cost.map(|cost| cost.meso),
+                results: threshold.results,
+            };
+            if exact {
+                let distribution = simulator.exact_distribution();
+                cells.extend(keeps.iter().map(|keep| {
+                    cell(
+                        None,
+                        ThresholdResults {
+                            keep: *keep,
+                            results: exact_results(
+                                cost,
+                                distribution.probability_at_least(*keep as f64),
+                                0,
+                            ),
+                        },
+                    )
+                }));
+            } else {
+                if importance {
+                    simulator = simulator.with_importance_sampling(keep);
+                }
+                simulator = simulator.with_thresholds(keeps[..keeps.len() - 1].to_vec());
+                let result = match precision {
+                    Some(precision) => {
+                        simulator.run_until(precision, keep, 0, seed, |_| {}, |_| {})
+                    }
+                    None => simulator.run(trials, keep, 0, seed, |_| {}),
+                };
+                let mut thresholds = simulation_thresholds(cost, &result, keeps, 0);
+                if thresholds.is_empty() {
+                    thresholds.push(ThresholdResults {
+                        keep,
+                        results: simulation_results(cost, &result, 0),
+                    });
+                }
+                cells.extend(
+                    thresholds
+                        .into_iter()
+                        .map(|threshold| cell(Some(result.trials), threshold)),
+                );
+            }
+            bar.inc(1);
+        }
+    }
+    bar.finish_and_clear();
+    cells
+}
+
+/// Settings of a sweep or advisor run over the given flame types and level brackets.
+fn sweep_settings(
+    matches: &ArgMatches,
+    formula: &ScoreFormula,
+    flametypes: &[FlameType],
+    levels: &[EquipLevel],
+    tables: &GameTables,
+    seed: u64,
+) -> SweepSettings {
+    let precision = precision(matches);
+    let exact = matches.get_flag("exact");
+    let item_type = item_type(matches);
+    SweepSettings {
+        method: if exact { "exact" } else { "simulation" },
+        seed: (!exact).then_some(seed),
+        precision: precision
+            .filter(|_| !exact)
+            .map(|precision| precision.relative),
+        max_trials: precision
+            .filter(|_| !exact)
+            .map(|precision| precision.max_trials),
+        importance_sampling: matches.get_flag("importance") && !exact,
+        flametypes: flametypes
+            .iter()
+            .map(|flametype| flametype.to_string())
+            .collect(),
+        levels: levels.iter().map(|level| level.to_string()).collect(),
+        item: matches.get_one::<String>("item").unwrap().clone(),
+        base_att: match item_type {
+            ItemType::Weapon { base_att } => Some(base_att),
+            ItemType::Armor => None,
+        },
+        noboss: matches.get_flag("noboss"),
+        stat: scored_stat(matches).map(|stat| stat.to_string()),
+        job: matches.get_one::<Job>("job").map(|job| job.to_string()),
+        profile: matches.get_one::<String>("profile").cloned(),
+        formula: formula.to_string(),
+        keep: matches.get_one::<Vec<f32>>("keep").unwrap().clone(),
+        meso_per_nx: tables.meso_per_nx(),
+    }
+}
+
+/// Prints the settings line of a sweep or advisor run, titled `title`.
+fn print_sweep_settings(
+    matches: &ArgMatches,
+    title: &str,
+    scoring: &str,
+    formula: &ScoreFormula,
+    seed: u64,
+) {
+    let item = matches.get_one::<String>("item").unwrap();
+    let importance = matches.get_flag("importance");
+    match (matches.get_flag("exact"), precision(matches)) {
+        (true, _) => println!("{} (exact) - {}, Item: {} \n", title, scoring, item),
+        (false, precision) => println!(
+            "{} - Trials: {}, Seed: {}, {}, Item: {} \n",
+            title,
+            match precision {
+                Some(precision) => format!("{}% precision", precision.relative * 100.0),
+                None => matches
+                    .get_one::<u64>("trials")
+                    .unwrap()
+                    .separate_with_commas(),
+            },
+            seed,
+            scoring,
+            item
+        ),
+    }
+    if matches.get_flag("noboss") {
+        println!("Noboss: true")
+    }
+    if importance && !matches.get_flag("exact") {
+        println!("Importance sampling: rolls are biased toward the target and reweighted \n");
+    }
+    if matches.contains_id("job") || matches.contains_id("profile") {
+        println!("Score: {} \n", formula);
+    }
+}
+
+/// Runs every combination of the flame types and level brackets given to `sweep`, with the
+/// same seed, and prints the odds, average flames and cost of each as matrices.
+fn sweep(matches: &ArgMatches, dir: &str, weights: &ScoreWeights, tables: &GameTables) {
+    let flametypes = selected(matches, "flametype", FlameType::value_variants());
+    let brackets: Vec<EquipLevel> = LevelBracket::value_variants()
+        .iter()
+        .map(|bracket| EquipLevel::from(*bracket))
+        .collect();
+    let levels = selected(matches, "level", &brackets);
+    let keeps = matches.get_one::<Vec<f32>>("keep").unwrap();
+    let format = *matches.get_one::<OutputFormat>("format").unwrap();
+    let (scoring, formula) = scoring(matches, dir, weights);
+    let seed = seed(matches);
+
+    let now = Instant::now();
+    let cells = sweep_cells(matches, &formula, &flametypes, &levels, tables, seed);
+    let settings = sweep_settings(matches, &formula, &flametypes, &levels, tables, seed);
+    let report = SweepReport::new(settings, cells);
+    print_report(format, &report);
+    if format != OutputFormat::Text {
+        return;
+    }
+
+    print_sweep_settings(matches, "Sweep", &scoring, &formula, seed);
+
+    let results = |f: usize, l: usize, k: usize| {
+        &report.cells[(f * levels.len() + l) * keeps.len() + k].results
+    };
+    for (k, keep) in keeps.iter().enumerate() {
+        println!("Flames over {} flamescore:", keep);
+        print_matrix("Odds", &flametypes, &levels, |f, l| {
+            format!("{:.4}%", results(f, l, k).probability * 100.0)
+        });
+        print_matrix("Flames", &flametypes, &levels, |f, l| {
+            format_average_flames(results(f, l, k).average_flames)
+        });
+        let costs = (0..flametypes.len())
+            .any(|f| (0..levels.len()).any(|l| results(f, l, k).average_cost.is_some()));
+        if costs {
+            print_matrix("Cost", &flametypes, &levels, |f, l| {
+                results(f, l, k)
+                    .average_cost
+                    .map_or("-".to_owned(), format_cost)
+            });
+        }
+    }
+    if report.cells.iter().any(|cell| cell.results.low_count) {
+        println!(
+            "Warning: fewer than {} flames reached the target in some combinations, too few for \
+             reliable estimates. Increase --trials or use --exact. \n",
+            flame_rs::confidence::MIN_RELIABLE_COUNT
+        );
+    }
+    println!("time: {:.3?}", now.elapsed());
+}
+
+/// Ranks every flame type by the meso it takes to reach each `--keep` target at a level
+/// bracket, on average and at the percentiles of luck in [`report::LUCK_PERCENTILES`].
+fn advise(matches: &ArgMatches, dir: &str, weights: &ScoreWeights, tables: &GameTables) {
+    let flametypes = FlameType::value_variants();
+    let levels = [*matches.get_one::<EquipLevel>("level").unwrap()];
+    let keeps = matches.get_one::<Vec<f32>>("keep").unwrap();
+    let format = *matches.get_one::<OutputFormat>("format").unwrap();
+    let (scoring, formula) = scoring(matches, dir, weights);
+    let seed = seed(matches);
+
+    let now = Instant::now();
+    let cells = sweep_cells(matches, &formula, flametypes, &levels, tables, seed);
+    let settings = sweep_settings(matches, &formula, flametypes, &levels, tables, seed);
+    let ranking: Vec<Advice> = (0..keeps.len())
+        .flat_map(|k| Advice::rank(cells.iter().skip(k).step_by(keeps.len()).cloned().collect()))
+        .collect();
+    let report = AdviceReport::new(settings, ranking);
+    print_report(format, &report);
+    if format != OutputFormat::Text {
+        return;
+    }
+
+    print_sweep_settings(
+        matches,
+        &format!("Advice - Level: {}", levels[0]),
+        &scoring,
+        &formula,
+        seed,
+    );
+    for ranking in report.ranking.chunks(flametypes.len()) {
+        println!(
+            "Cheapest flames to reach {} flamescore:",
+            ranking[0].cell.keep
+        );
+        let mut columns: Vec<(String, Vec<Option<String>>)> = vec![
+            (
+                "#".to_owned(),
+                ranking
+                    .iter()
+                    .map(|advice| advice.rank.map(|rank| rank.to_string()))
+                    .collect(),
+            ),
+            (
+                "Flame type".to_owned(),
+                ranking
+                    .iter()
+                    .map(|advice| Some(advice.cell.flametype.clone()))
+                    .collect(),
+            ),
+            (
+                "Odds".to_owned(),
+                ranking
+                    .iter()
+                    .map(|advice| Some(format!("{:.4}%", advice.cell.results.probability * 100.0)))
+                    .collect(),
+            ),
+            (
+                "Average flames".to_owned(),
+                ranking
+                    .iter()
+                    .map(|advice| {
+                        let flames = advice.cell.results.average_flames?;
+                        Some((flames.ceil() as u64).separate_with_commas())
+                    })
+                    .collect(),
+            ),
+            (
+                "Average cost".to_owned(),
+                ranking
+                    .iter()
+                    .map(|advice| advice.cell.results.average_cost.map(format_cost))
+                    .collect(),
+            ),
+        ];
+        for (l, percentile) in report::LUCK_PERCENTILES.iter().enumerate() {
+            columns.push((
+                format!("p{} cost", percentile),
+                ranking
+                    .iter()
+                    .map(|advice| {
+                        let luck = advice.luck[l];
+                        Some(format!("{} (#{})", format_cost(luck.cost?), luck.rank?))
+                    })
+                    .collect(),
+            ));
+        }
+        print_table(columns, ranking.len());
+
+        match ranking[0].rank {
+            Some(_) => {
+                println!("Cheapest on average: {}", ranking[0].cell.flametype);
+                for (l, percentile) in report::LUCK_PERCENTILES.iter().enumerate() {
+                    if let Some(advice) =
+                        ranking.iter().find(|advice| advice.luck[l].rank == Some(1))
+                    {
+                        println!(
+                            "Cheapest in {}% of attempts: {} (at most {} flames, {})",
+                            percentile,
+                            advice.cell.flametype,
+                            advice.luck[l].flames.unwrap_or(0).separate_with_commas(),
+                            format_cost(advice.luck[l].cost.unwrap_or(0.0))
+                        );
+                    }
+                }
+            }
+            None => println!("No flame type with a price reaches this target."),
+        }
+        println!();
+    }
+    if report
+        .ranking
+        .iter()
+        .any(|advice| advice.cell.results.low_count)
+    {
+        println!(
+            "Warning: fewer than {} flames reached the target with some flame types, too few for \
+             reliable estimates. Increase --trials or use --exact. \n",
+            flame_rs::confidence::MIN_RELIABLE_COUNT
+        );
+    }
+    println!("time: {:.3?}", now.elapsed());
+}
+
+fn main() {
+    let matches = cli().get_matches();
+
+    if let Some(shell) = matches.get_one::<Shell>("completions") {
+        generate(*shell, &mut cli(), "flame_rs", &mut io::stdout());
+        return;
+    }
+
+    let mut dir = String::new();
+
+    match inner_main() {
+        Ok(path) => dir = path.as_path().display().to_string(),
+        Err(e) => {
+            eprintln!("Error determining executable path: {}", e);
+        }
+    }
+
+    let weights = match ScoreWeights::load(&dir) {
+        Ok(weights) => weights,
+        Err(e) => {
+            eprintln!("error parsing: {:?}", e);
+            ScoreWeights::default()
+        }
+    };
+
+    // arguments shared with subcommands are only set on the subcommand's matches
+    let args = matches.subcommand().map_or(&matches, |(_, args)| args);
+    let tables = match args.get_one::<PathBuf>("tables") {
+        Some(path) => match GameTables::load(&path.display().to_string()) {
+            Ok(tables) => tables,
+            Err(e) => {
+                eprintln!("error loading tables from {}: {}", path.display(), e);
+                process::exit(1);
+            }
+        },
+        None => GameTables::bundled(),
+    };
+    let tables = match args.get_one::<f64>("meso-per-nx") {
+        Some(rate) => tables.with_meso_per_nx(*rate).unwrap_or_else(|e| {
+            eprintln!("error: {}", e);
+            process::exit(1);
+        }),
+        None => tables,
+    };
+
+    match matches.subcommand() {
+        Some(("sweep", matches)) => {
+            sweep(matches, &dir, &weights, &tables);
+            return;
+        }
+        Some(("advise", matches)) => {
+            advise(matches, &dir, &weights, &tables);
+            return;
+        }
+        _ => {}
+    }
+
+    let precision = precision(&matches);
+    // the most trials that can run, used to size the progress bar and top flames
+    let trials = match &precision {
+        Some(precision) => &precision.max_trials,
+        None => matches.get_one::<u64>("trials").unwrap(),
+    };
+    // the highest target drives the run, the others are counted from the same flames
+    let keeps = matches.get_one::<Vec<f32>>("keep").unwrap();
+    let keep = keeps.last().unwrap();
+    let level = *matches.get_one::<EquipLevel>("level").unwrap();
+    let flametype = *matches.get_one::<FlameType>("flametype").unwrap();
+    let cost = tables.flame_cost(flametype);
+
+    let mut top: usize = 1;
+    if let Some(tops) = matches.get_one::<usize>("top") {
+        if *tops > 1000 {
+            top = 1000;
+        } else if *tops > *trials as usize {
+            top = *trials as usize;
+        } else {
+            top = *tops;
+        }
+    }
+
+    // single-target results give the odds within the largest checkpoint
+    let checkpoints = matches.get_one::<Vec<i32>>("chance");
+    let chance = checkpoints.map_or(0, |checkpoints| *checkpoints.last().unwrap());
+    let curve_flames: Vec<i32> = match checkpoints {
+        Some(_) if matches.get_flag("curve") => {
+            if chance > MAX_CURVE_FLAMES {
+                eprintln!(
+                    "error: --curve lists at most {} flames, give --chance checkpoints instead",
+                    MAX_CURVE_FLAMES.separate_with_commas()
+                );
+                process::exit(1);
+            }
+            (1..=chance).collect()
+        }
+        Some(checkpoints) if checkpoints.len() > 1 || matches.contains_id("chance-csv") => {
+            checkpoints.clone()
+        }
+        _ => Vec::new(),
+    };
+
+    let noboss = matches.get_flag("noboss");
+
+    let item = matches.get_one::<String>("item").unwrap();
+    let item_type = item_type(&matches);
+
+    let job = matches.get_one::<Job>("job");
+    let profile = matches.get_one::<String>("profile");
+    let (scoring, formula) = scoring(&matches, &dir, &weights);
+
+    let importance = matches.get_flag("importance");
+    let mut simulator =
+        match FlameSimulator::new(formula, level, flametype, noboss, item_type, &tables) {
+            Ok(simulator) if importance => simulator.with_importance_sampling(*keep),
+            Ok(simulator) => simulator,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                process::exit(1);
+            }
+        };
+    let show_distribution = matches.get_flag("distribution");
+    if show_distribution {
+        simulator = simulator.with_score_distribution();
+    }
+    // beating the current flame and improving on it are counted as extra thresholds
+    let current = matches.get_one::<f32>("current").copied();
+    let mut thresholds = keeps[..keeps.len() - 1].to_vec();
+    if let Some(current) = current {
+        thresholds.push(current.next_up());
+        if let Some(points) = matches.get_one::<f32>("improve") {
+            thresholds.push(current + points);
+        }
+    }
+    // the best score out of a budget is worked out from the whole score distribution
+    let budget = budget_flames(&matches, flametype, cost);
+    if budget.is_some() {
+        simulator = simulator.with_score_distribution();
+    }
+    if !thresholds.is_empty() {
+        simulator = simulator.with_thresholds(thresholds);
+    }
+
+    let format = *matches.get_one::<OutputFormat>("format").unwrap();
+    let settings = Settings {
+        method: "simulation",
+        trials: Some(*trials),
+        seed: None,
+        precision: precision.map(|precision| precision.relative),
+        max_trials: precision.map(|precision| precision.max_trials),
+        importance_sampling: importance,
+        flametype: flametype.to_string(),
+        level: level.to_string(),
+        item: item.clone(),
+        base_att: match item_type {
+            ItemType::Weapon { base_att } => Some(base_att),
+            ItemType::Armor => None,
+        },
+        noboss,
+        stat: scored_stat(&matches).map(|stat| stat.to_string()),
+        job: job.map(|job| job.to_string()),
+        profile: profile.cloned(),
+        formula: simulator.formula().to_string(),
+        keep: *keep,
+        flame_cost: cost.map(|cost| cost.meso),
+        meso_per_nx: tables.meso_per_nx(),
+    };
+
+    let now = Instant::now();
+
+    if matches.get_flag("exact") {
+        let distribution = simulator.exact_distribution();
+        if format != OutputFormat::Text {
+            let settings = Settings {
+                method: "exact",
+                trials: None,
+                importance_sampling: false,
+                ..settings
+            };
+            let results = exact_results(
+                cost,
+                distribution.probability_at_least(*keep as f64),
+                chance,
+            );
+            let curve = exact_curve(results.probability, &curve_flames);
+            write_chance_csv(&matches, &curve);
+            let summary = show_distribution.then(|| DistributionSummary::new(&distribution));
+            let mut report = Report::new(settings, results, Vec::new(), summary)
+                .with_thresholds(exact_thresholds(cost, &distribution, keeps, chance))
+                .with_chance_curve(curve);
+            if let Some(current) = current {
+                report = report.with_current(exact_current(
+                    &matches,
+                    cost,
+                    &distribution,
+                    current,
+                    budget,
+                    chance,
+                ));
+            }
+            if let Some(flames) = budget {
+                report = report.with_best_of(best_of(&distribution, flames, cost));
+            }
+            print_report(format, &report);
+            return;
+        }
+
+        println!(
+            "Settings - Flametype: {}, {}, Level: {}, Item: {} \n",
+            flametype, scoring, level, item
+        );
+        if noboss {
+            println!("Noboss: {}", noboss)
+        }
+        if job.is_some() || profile.is_some() {
+            println!("Score: {} \n", simulator.formula());
+        }
+        let thresholds = exact_thresholds(cost, &distribution, keeps, chance);
+        print_exact(
+            cost,
+            &distribution,
+            *keep,
+            chance,
+            &thresholds,
+            show_distribution,
+        );
+        let curve = exact_curve(
+            distribution.probability_at_least(*keep as f64),
+            &curve_flames,
+        );
+        write_chance_csv(&matches, &curve);
+        if !curve.is_empty() {
+            println!();
+            print_chance_curve(&curve);
+        }
+        if show_distribution {
+            println!();
+            print_distribution(
+                "Flamescore distribution:",
+                &DistributionSummary::new(&distribution),
+            );
+        }
+        if let Some(flames) = budget {
+            println!();
+            print_best_of(&best_of(&distribution, flames, cost));
+        }
+        if let Some(current) = current {
+            println!();
+            print_current(&exact_current(
+                &matches,
+                cost,
+                &distribution,
+                current,
+                budget,
+                chance,
+            ));
+        }
+        println!("time: {:.3?}", now.elapsed());
+        return;
+    }
+
+    let seed = seed(&matches);
+
+    let bar = match format {
+        OutputFormat::Text => ProgressBar::new(*trials),
+        _ => ProgressBar::hidden(),
+    };
+    let result = match precision {
+        Some(precision) => {
+            bar.set_style(
+                ProgressStyle::with_template("{wide_bar} {pos}/{len} {msg}")
+                    .expect("progress template is valid"),
+            );
+            let result = simulator.run_until(
+                precision,
+                *keep,
+                top,
+                seed,
+                |rolled| bar.inc(rolled),
+                |result| {
+                    let interval = result.probability_interval();
+                    bar.set_message(format!(
+                        "{:.4}% ({:.4}% - {:.4}%)",
+                        result.probability() * 100.0,
+                        interval.lower * 100.0,
+                        interval.upper * 100.0
+                    ));
+                },
+            );
+            bar.set_length(result.trials);
+            result
+        }
+        None => simulator.run(*trials, *keep, top, seed, |rolled| bar.inc(rolled)),
+    };
+    bar.finish();
+
+    if format != OutputFormat::Text {
+        let results = simulation_results(cost, &result, chance);
+        let settings = Settings {
+            trials: Some(result.trials),
+            seed: Some(seed),
+            ..settings
+        };
+        let summary = result
+            .distribution
+            .as_ref()
+            .filter(|_| show_distribution)
+            .map(DistributionSummary::new);
+        let curve = simulation_curve(&result, &curve_flames);
+        write_chance_csv(&matches, &curve);
+        let mut report = Report::new(settings, results, RankedFlame::rank(&result.top), summary)
+            .with_thresholds(simulation_thresholds(cost, &result, keeps, chance))
+            .with_chance_curve(curve);
+        if let Some(current) = current {
+            report = report.with_current(simulation_current(
+                &matches,
+                cost,
+                &result,
+                keeps.len(),
+                current,
+                budget,
+                chance,
+            ));
+        }
+        if let Some((flames, distribution)) = budget.zip(result.distribution.as_ref()) {
+            report = report.with_best_of(best_of(distribution, flames, cost));
+        }
+        print_report(format, &report);
+        return;
+    }
+
+    println!(
+        "Settings - Trials: {}, Seed: {}, Flametype: {}, {}, Level: {}, Item: {} \n",
+        result.trials.separate_with_commas(),
+        seed,
+        flametype,
+        scoring,
+        level,
+        item
+    );
+    if let Some(precision) = precision {
+        let reached = result.relative_precision() <= precision.relative;
+        println!(
+            "Precision: {}% {} (±{:.2}% after {} trials) \n",
+            precision.relative * 100.0,
+            if reached { "reached" } else { "not reached" },
+            result.relative_precision() * 100.0,
+            result.trials.separate_with_commas()
+        );
+    }
+    if noboss {
+        println!("Noboss: {}", noboss)
+    }
+    if importance {
+        println!("Importance sampling: rolls are biased toward the target and reweighted \n");
+    }
+    if job.is_some() || profile.is_some() {
+        println!("Score: {} \n", simulator.formula());
+    }
+    println!("Results:");
+    if keeps.len() > 1 {
+        if importance {
+            println!("Flames count biased rolls.");
+        }
+        print_thresholds(&simulation_thresholds(cost, &result, keeps, chance));
+    } else {
+        print_simulation(cost, &result, *keep, chance, importance);
+    }
+
+    let curve = simulation_curve(&result, &curve_flames);
+    write_chance_csv(&matches, &curve);
+    if !curve.is_empty() {
+        print_chance_curve(&curve);
+    }
+
+    if let Some(distribution) = result.distribution.as_ref().filter(|_| show_distribution) {
+        print_distribution(
+            "Flamescore distribution:",
+            &DistributionSummary::new(distribution),
+        );
+    }
+
+    if let Some((flames, distribution)) = budget.zip(result.distribution.as_ref()) {
+        print_best_of(&best_of(distribution, flames, cost));
+    }
+
+    if let Some(current) = current {
+        print_current(&simulation_current(
+            &matches,
+            cost,
+            &result,
+            keeps.len(),
+            current,
+            budget,
+            chance,
+        ));
+    }
+
+    if top > 1 {
+        println!("Top {} flames:", top);
+        for (number, flame) in (1..).zip(result.top.iter()) {
+            println!(
+                "#{}: {:?} with score: {:.2} \n",
+                number, flame.lines, flame.score
+            );
+        }
+    } else {
+        for flame in result.top.iter() {
+            println!("Best flame:");
+            for el in flame.lines.iter() {
+                if el.1 > 0 {
+                    println!("{:?}, {:?}", el.0, el.1);
+                }
+            }
+            println!("\nscore: {:.2} \n", flame.score);
+        }
+    }
+    let elapsed = now.elapsed();
+    println!("time: {:.3?}", elapsed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_thresholds() {
+        assert_eq!(parse_thresholds("120").unwrap(), vec![120.0]);
+        // ranges include their end, and the targets come out sorted without repeats
+        assert_eq!(
+            parse_thresholds("130, 100..120:10,110").unwrap(),
+            vec![100.0, 110.0, 120.0, 130.0]
+        );
+        // an end that falls between steps is left out
+        assert_eq!(
+            parse_thresholds("100..125:10").unwrap(),
+            vec![100.0, 110.0, 120.0]
+        );
+        let tenths = parse_thresholds("1..2:0.1").unwrap();
+        assert_eq!(tenths.len(), 11);
+        assert!((tenths[10] - 2.0).abs() < 1e-5);
+    }
+
+    #[test]
+    fn rejects_bad_thresholds() {
+        for value in [
+            "",
+            "abc",
+            "100..200",
+            "200..100:10",
+            "100..200:0",
+            "100..200:-5",
+            "inf",
+        ] {
+            assert!(parse_thresholds(value).is_err(), "'{}' was accepted", value);
+        }
+        assert_eq!(
+            parse_thresholds("1..999:1").unwrap().len(),
+            MAX_THRESHOLDS - 1
+        );
+        assert_eq!(parse_thresholds("1..1000:1").unwrap().len(), MAX_THRESHOLDS);
+        assert!(parse_thresholds("1..1001:1").is_err());
+        assert!(parse_thresholds("0,1..1000:1").is_err());
+        assert!(parse_thresholds("1..1e9:0.001").is_err());
+    }
+
+    #[test]
+    fn parses_meso() {
+        assert_eq!(parse_meso("2b").unwrap(), 2e9);
+        assert_eq!(parse_meso("500M").unwrap(), 5e8);
+        assert_eq!(parse_meso("1.5t").unwrap(), 1.5e12);
+        assert_eq!(parse_meso("250k").unwrap(), 2.5e5);
+        assert_eq!(parse_meso("250,000,000").unwrap(), 2.5e8);
+        assert_eq!(parse_meso("250_000_000").unwrap(), 2.5e8);
+        for value in ["", "b", "0", "-2b", "2x", "inf"] {
+            assert!(parse_meso(value).is_err(), "'{}' was accepted", value);
+        }
+    }
+
+    #[test]
+    fn parses_checkpoints() {
+        assert_eq!(parse_checkpoints("50").unwrap(), vec![50]);
+        assert_eq!(
+            parse_checkpoints("100, 10,50,10").unwrap(),
+            vec![10, 50, 100]
+        );
+        for value in ["", "0", "-5", "1.5", "10,,20", "abc"] {
+            assert!(
+                parse_checkpoints(value).is_err(),
+                "'{}' was accepted",
+                value
+            );
+        }
+        let most: Vec<String> = (1..=MAX_THRESHOLDS).map(|i| i.to_string()).collect();
+        assert_eq!(
+            parse_checkpoints(&most.join(",")).unwrap().len(),
+            MAX_THRESHOLDS
+        );
+        assert!(parse_checkpoints(&format!("{},0", most.join(","))).is_err());
+    }
+
+    #[test]
+    fn rounds_costs() {
+        assert_eq!(format_cost(0.89376e9), "0.894b");
+        assert_eq!(format_cost(12.346e9), "12.35b");
+        assert_eq!(format_cost(123.46e9), "123.5b");
+        assert_eq!(format_cost(0.15e9), "0.15b");
+        assert_eq!(format_cost(6e9), "6b");
+        assert_eq!(format_cost(0.0), "0b");
+        assert_eq!(format_cost(1.5e12), "1.5T");
+        assert_eq!(format_cost(1234.56e12), "1,235T");
+    }
+}
@@ -0,0 +1,1042 @@
+//! Machine-readable run reports, written as JSON, NDJSON or CSV.
+//!
+//! The layout of every format is described in the README and versioned with
+//! [`SCHEMA_VERSION`]: fields are only ever added within a version, never renamed or removed.
+//! New CSV columns can land anywhere in the row, so CSV is read by header name, not position.
+
+use crate::confidence::Interval;
+use crate::exact::{Bin, ScoreDistribution};
+use crate::flame::{Flame, FLAME_STATS};
+use crate::tables::FlameCost;
+use serde::ser::{SerializeMap, Serializer};
+use serde::Serialize;
+use std::io::{self, Write};
+
+/// Version of the report layout.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Report that can be written in every machine-readable format.
+pub trait WriteReport: Serialize {
+    /// Writes the report as a single JSON document.
+    fn write_json<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writeln!(writer)
+    }
+
+    /// Writes one JSON object per line, each with a `type` field and the `schema_version`.
+    fn write_ndjson<W: Write>(&self, writer: W) -> io::Result<()>;
+
+    /// Writes a CSV table with a header row.
+    fn write_csv<W: Write>(&self, writer: W) -> io::Result<()>;
+}
+
+/// Average meso spent to reach a threshold, for flame types with a price.
+///
+/// Flames are only used whole, so the average amount is rounded up first.
+pub fn average_cost(cost: Option<FlameCost>, average_flames: f64) -> Option<f64> {
+    Some(average_flames.ceil() * cost?.meso)
+}
+
+/// Interval of the average meso spent, from an interval of the average flames needed.
+pub fn average_cost_interval(
+    cost: Option<FlameCost>,
+    average_flames: Interval,
+) -> Option<Interval> {
+    Some(Interval {
+        lower: average_cost(cost, average_flames.lower)?,
+        upper: average_cost(cost, average_flames.upper)?,
+    })
+}
+
+/// Flames needed to reach a threshold in `percentile`% of attempts, for flames reaching it
+/// with `probability`, or `None` if it's never reached.
+///
+/// The flames needed follow a geometric distribution, so this is the smallest `n` with
+/// `1 - (1 - probability)^n >= percentile / 100`.
+pub fn flames_for_luck(probability: f64, percentile: f64) -> Option<u64> {
+    if probability <= 0.0 {
+        return None;
+    }
+    if probability >= 1.0 {
+        return Some(1);
+    }
+    let flames = (-percentile / 100.0).ln_1p() / (-probability).ln_1p();
+    Some((flames.ceil() as u64).max(1))
+}
+
+/// Percentiles of luck the flames and meso spent to reach a threshold are reported at.
+pub const SPEND_PERCENTILES: [f64; 4] = [50.0, 75.0, 90.0, 99.0];
+
+/// Flames and meso spent to reach a threshold in `percentile`% of attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Spend {
+    pub percentile: f64,
+    pub flames: u64,
+    /// 95% confidence interval of `flames`, `None` for exact results.
+    pub flames_interval: Option<Interval>,
+    /// Meso spent, `None` if the flame type has no price.
+    pub cost: Option<f64>,
+    pub cost_interval: Option<Interval>,
+}
+
+impl Spend {
+    /// Spend at every percentile of [`SPEND_PERCENTILES`] for flames reaching a threshold with
+    /// `probability`, or none if it's never reached.
+    ///
+    /// The interval of the odds, if known, gives the interval of the flames: the likeliest
+    /// odds need the fewest flames.
+    pub fn percentiles(
+        cost: Option<FlameCost>,
+        probability: f64,
+        probability_interval: Option<Interval>,
+    ) -> Vec<Spend> {
+        let meso = |flames: f64| Some(flames * cost?.meso);
+        SPEND_PERCENTILES
+            .iter()
+            .filter_map(|percentile| {
+                let flames = flames_for_luck(probability, *percentile)?;
+                let flames_interval = probability_interval.map(|interval| {
+                    interval.map_rev(|probability| {
+                        flames_for_luck(probability, *percentile)
+                            .map_or(f64::INFINITY, |flames| flames as f64)
+                    })
+                });
+                Some(Spend {
+                    percentile: *percentile,
+                    flames,
+                    flames_interval,
+                    cost: meso(flames as f64),
+                    cost_interval: flames_interval.and_then(|interval| {
+                        Some(Interval {
+                            lower: meso(interval.lower)?,
+                            upper: meso(interval.upper)?,
+                        })
+                    }),
+                })
+            })
+            .collect()
+    }
+}
+
+/// Settings a report was produced with.
+#[derive(Debug, Clone, Serialize)]
+pub struct Settings {
+    /// `simulation` or `exact`.
+    pub method: &'static str,
+    /// Amount of simulated flames, `None` for exact results.
+    pub trials: Option<u64>,
+    /// Seed of the random rolls, `None` for exact results.
+    pub seed: Option<u64>,
+    /// Target relative half-width of the probability interval, as a fraction, if the trials
+    /// were picked adaptively.
+    pub precision: Option<f64>,
+    /// Trial cap of an adaptive run.
+    pub max_trials: Option<u64>,
+    /// Whether rolls were biased toward `keep` and reweighted.
+    pub importance_sampling: bool,
+    pub flametype: String,
+    pub level: String,
+    pub item: String,
+    pub base_att: Option<u16>,
+    pub noboss: bool,
+    /// Stat archetype the flames are scored for, `None` for score profiles.
+    pub stat: Option<String>,
+    pub job: Option<String>,
+    pub profile: Option<String>,
+    /// Score formula, with every coefficient.
+    pub formula: String,
+    pub keep: f32,
+    /// Meso value of one flame, `None` if the flame type has no price.
+    pub flame_cost: Option<f64>,
+    /// Meso per NX that NX prices were converted with.
+    pub meso_per_nx: f64,
+}
+
+/// Odds of reaching the threshold within an amount of flames.
+#[derive(Debug, Clone, Serialize)]
+pub struct ChanceWithin {
+    pub flames: i32,
+    pub probability: f64,
+    /// 95% confidence interval of `probability`, `None` for exact results.
+    pub interval: Option<Interval>,
+}
+
+/// Outcome of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Results {
+    /// Amount of flames that reached the threshold, `None` for exact results.
+    pub count: Option<u64>,
+    /// Fraction of flames that reached the threshold, from 0 to 1.
+    pub probability: f64,
+    /// 95% confidence interval of `probability`, `None` for exact results.
+    pub probability_interval: Option<Interval>,
+    /// Average flames needed to reach the threshold, `None` if it was never reached.
+    pub average_flames: Option<f64>,
+    pub average_flames_interval: Option<Interval>,
+    /// Average meso spent, `None` if the flame type isn't bought with meso.
+    pub average_cost: Option<f64>,
+    pub average_cost_interval: Option<Interval>,
+    pub chance_within: Option<ChanceWithin>,
+    /// Whether too few flames reached the threshold for the estimates to be meaningful.
+    pub low_count: bool,
+    /// Flames and meso spent at every percentile of [`SPEND_PERCENTILES`], empty if the
+    /// threshold was never reached.
+    pub spend: Vec<Spend>,
+}
+
+/// Outcome for one of several thresholds given at once.
+#[derive(Debug, Clone, Serialize)]
+pub struct ThresholdResults {
+    pub keep: f32,
+    #[serde(flatten)]
+    pub results: Results,
+}
+
+/// Odds of improving on the flame an item already has, and how to spend a budget on it.
+#[derive(Debug, Clone, Serialize)]
+pub struct CurrentFlame {
+    pub score: f32,
+    /// Whether a new flame only replaces the current one if it scores higher.
+    pub keep_old: bool,
+    /// Outcome for flames scoring higher than the current one.
+    pub beat: Results,
+    pub improve: Option<Improvement>,
+    pub budget: Option<Budget>,
+}
+
+/// Outcome for flames scoring at least `points` higher than the current one.
+#[derive(Debug, Clone, Serialize)]
+pub struct Improvement {
+    pub points: f32,
+    #[serde(flatten)]
+    pub results: Results,
+}
+
+/// Best way to spend a budget of flames on improving the current flame.
+#[derive(Debug, Clone, Serialize)]
+pub struct Budget {
+    pub flames: u32,
+    /// Odds of rolling a flame that beats the current one within the budget.
+    pub chance_to_beat: f64,
+    /// Whether flaming on is expected to be worth more than stopping now, counting the
+    /// price of every flame when `--point-value` is given.
+    pub keep_flaming: bool,
+    /// Expected final score when following the stop thresholds.
+    pub expected_score: f64,
+    /// Expected flames used when following the stop thresholds.
+    pub expected_flames: f64,
+    /// Expected meso spent on those flames, for flame types with a price.
+    pub expected_cost: Option<f64>,
+    /// Scores to stop at by flames left. With `keep_old` the threshold doesn't depend on
+    /// the flames left, so there is a single one for the whole budget.
+    pub stop_thresholds: Vec<StopThreshold>,
+}
+
+impl Budget {
+    /// Budget advice for an item scoring `current`, given the odds `beat` of a flame beating
+    /// it. Flames are worth their price in score points when `point_value`, the meso one
+    /// point is worth, is given, and free otherwise.
+    pub fn new(
+        distribution: &ScoreDistribution,
+        current: f32,
+        flames: u32,
+        keep_old: bool,
+        cost: Option<FlameCost>,
+        point_value: Option<f64>,
+        beat: f64,
+    ) -> Budget {
+        let flame_cost = cost
+            .zip(point_value)
+            .map_or(0.0, |(cost, point_value)| cost.meso / point_value);
+        let rule = distribution.stop_rule(current as f64, flames, keep_old, flame_cost);
+        let stop_thresholds = if keep_old {
+            rule.thresholds
+                .iter()
+                .map(|score| StopThreshold {
+                    flames_left: flames,
+                    score: *score,
+                })
+                .collect()
+        } else {
+            (1..)
+                .zip(&rule.thresholds)
+                .map(|(flames_left, score)| StopThreshold {
+                    flames_left,
+                    score: *score,
+                })
+                .collect()
+        };
+        Budget {
+            flames,
+            chance_to_beat: 1.0 - (1.0 - beat).powi(flames as i32),
+            keep_flaming: rule.keep_flaming,
+            expected_score: rule.expected_score,
+            expected_flames: rule.expected_flames,
+            expected_cost: cost.map(|cost| rule.expected_flames * cost.meso),
+            stop_thresholds,
+        }
+    }
+}
+
+/// Score worth stopping at with some flames left.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct StopThreshold {
+    pub flames_left: u32,
+    pub score: f64,
+}
+
+/// One of the best flames of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct RankedFlame {
+    pub rank: usize,
+    pub score: f32,
+    /// Stat totals, keyed by the names in [`FLAME_STATS`].
+    #[serde(serialize_with = "serialize_lines")]
+    pub lines: Vec<(&'static str, u16)>,
+}
+
+impl RankedFlame {
+    /// Ranks flames in the given order, starting at 1.
+    pub fn rank(flames: &[Flame]) -> Vec<RankedFlame> {
+        flames
+            .iter()
+            .enumerate()
+            .map(|(i, flame)| RankedFlame {
+                rank: i + 1,
+                score: flame.score,
+                lines: flame.lines.clone(),
+            })
+            .collect()
+    }
+}
+
+fn serialize_lines<S: Serializer>(
+    lines: &[(&'static str, u16)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(lines.len()))?;
+    for (stat, value) in lines {
+        map.serialize_entry(stat, value)?;
+    }
+    map.end()
+}
+
+/// Percentiles of the score reported in a [`DistributionSummary`].
+pub const PERCENTILES: [f64; 4] = [50.0, 90.0, 99.0, 99.9];
+
+/// Amount of bins in the histogram of a [`DistributionSummary`].
+pub const HISTOGRAM_BINS: usize = 20;
+
+/// Most scores listed in the `at_least` table of a [`DistributionSummary`].
+const AT_LEAST_ROWS: f64 = 20.0;
+
+/// Score below which a percentage of flames fall.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct Percentile {
+    pub percentile: f64,
+    pub score: f64,
+}
+
+/// Odds of a flame scoring at least `score`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct AtLeast {
+    pub score: f64,
+    pub probability: f64,
+}
+
+/// Summary statistics of a score distribution.
+#[derive(Debug, Clone, Serialize)]
+pub struct DistributionSummary {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub percentiles: Vec<Percentile>,
+    pub histogram: Vec<Bin>,
+    /// Odds of reaching every multiple of a round step, from the lowest to the highest score.
+    pub at_least: Vec<AtLeast>,
+}
+
+impl DistributionSummary {
+    /// Summarizes a distribution at every percentile of [`PERCENTILES`].
+    pub fn new(distribution: &ScoreDistribution) -> DistributionSummary {
+        DistributionSummary::with_percentiles(distribution, &PERCENTILES)
+    }
+
+    /// Summarizes a distribution at the given percentiles.
+    pub fn with_percentiles(
+        distribution: &ScoreDistribution,
+        percentiles: &[f64],
+    ) -> DistributionSummary {
+        let lowest = distribution.pmf.first().map_or(0.0, |(score, _)| *score);
+        let highest = distribution.pmf.last().map_or(0.0, |(score, _)| *score);
+        // the smallest of 1, 2, 5, 10, 20, 50, ... that lists at most AT_LEAST_ROWS scores
+        let mut step = 1.0;
+        for factor in [2.0, 2.5, 2.0].iter().cycle() {
+            if (highest - lowest) / step < AT_LEAST_ROWS {
+                break;
+            }
+            step *= factor;
+        }
+        let first = (lowest / step).ceil() as i64;
+        let last = (highest / step).floor() as i64;
+
+        DistributionSummary {
+            mean: distribution.mean(),
+            std_dev: distribution.std_dev(),
+            percentiles: percentiles
+                .iter()
+                .map(|percentile| Percentile {
+                    percentile: *percentile,
+                    score: distribution.percentile(percentile / 100.0),
+                })
+                .collect(),
+            histogram: distribution.histogram(HISTOGRAM_BINS),
+            at_least: (first..=last)
+                .map(|i| {
+                    let score = i as f64 * step;
+                    AtLeast {
+                        score,
+                        probability: distribution.probability_at_least(score),
+                    }
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Percentiles of the best score out of a budget, from an unlucky to a lucky spend.
+pub const BEST_OF_PERCENTILES: [f64; 6] = [10.0, 25.0, 50.0, 75.0, 90.0, 99.0];
+
+/// Distribution of the best score out of a budget of flames.
+#[derive(Debug, Clone, Serialize)]
+pub struct BestOf {
+    pub flames: u32,
+    /// Meso spent on the flames, `None` if the flame type has no price.
+    pub cost: Option<f64>,
+    /// `mean` is the expected best score.
+    #[serde(flatten)]
+    pub distribution: DistributionSummary,
+}
+
+/// Settings, results and best flames of a run.
+#[derive(Debug, Clone, Serialize)]
+pub struct Report {
+    pub schema_version: u32,
+    pub settings: Settings,
+    pub results: Results,
+    pub top: Vec<RankedFlame>,
+    /// Score distribution, if it was asked for.
+    pub distribution: Option<DistributionSummary>,
+    /// Odds of improving on the current flame, if one was given.
+    pub current: Option<CurrentFlame>,
+    /// Best score out of the budget, if one was given.
+    pub best_of: Option<BestOf>,
+    /// Odds of reaching the target within every `--chance` checkpoint, if several were given.
+    pub chance_curve: Vec<ChanceWithin>,
+    /// Outcome for every threshold, lowest first, if several were given. `results` is the
+    /// outcome for the highest one.
+    pub thresholds: Vec<ThresholdResults>,
+}
+
+impl Report {
+    pub fn new(
+        settings: Settings,
+        results: Results,
+        top: Vec<RankedFlame>,
+        distribution: Option<DistributionSummary>,
+    ) -> Report {
+        Report {
+            schema_version: SCHEMA_VERSION,
+            settings,
+            results,
+            top,
+            distribution,
+            current: None,
+            best_of: None,
+            chance_curve: Vec::new(),
+            thresholds: Vec::new(),
+        }
+    }
+
+    /// Adds the odds of improving on the current flame.
+    pub fn with_current(mut self, current: CurrentFlame) -> Report {
+        self.current = Some(current);
+        self
+    }
+
+    /// Adds the distribution of the best score out of a budget.
+    pub fn with_best_of(mut self, best_of: BestOf) -> Report {
+        self.best_of = Some(best_of);
+        self
+    }
+
+    /// Adds the odds of reaching the target within every checkpoint.
+    pub fn with_chance_curve(mut self, chance_curve: Vec<ChanceWithin>) -> Report {
+        self.chance_curve = chance_curve;
+        self
+    }
+
+    /// Adds the outcome for every threshold of a run with several.
+    pub fn with_thresholds(mut self, thresholds: Vec<ThresholdResults>) -> Report {
+        self.thresholds = thresholds;
+        self
+    }
+}
+
+impl WriteReport for Report {
+    /// Writes one JSON object per line: the settings, the results, every threshold, every point
+    /// of the chance curve, the current flame, the best score out of the budget, every top
+    /// flame, then the score distribution if there is one.
+    ///
+    /// Each object has a `type` field (`settings`, `results`, `threshold`, `chance`, `current`,
+    /// `best_of`, `flame` or `distribution`) and the `schema_version`.
+    fn write_ndjson<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        ndjson_line(&mut writer, "settings", &self.settings)?;
+        ndjson_line(&mut writer, "results", &self.results)?;
+        for threshold in &self.thresholds {
+            ndjson_line(&mut writer, "threshold", threshold)?;
+        }
+        for chance in &self.chance_curve {
+            ndjson_line(&mut writer, "chance", chance)?;
+        }
+        if let Some(current) = &self.current {
+            ndjson_line(&mut writer, "current", current)?;
+        }
+        if let Some(best_of) = &self.best_of {
+            ndjson_line(&mut writer, "best_of", best_of)?;
+        }
+        for flame in &self.top {
+            ndjson_line(&mut writer, "flame", flame)?;
+        }
+        if let Some(distribution) = &self.distribution {
+            ndjson_line(&mut writer, "distribution", distribution)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a CSV table with one row per top flame, or a single row without flame columns
+    /// filled in if there are none. Settings and results are repeated on every row; the score
+    /// distribution, chance curve, current flame and best score out of the budget don't fit the
+    /// table and are left out.
+    ///
+    /// With several thresholds, one row per threshold without flame columns comes first.
+    fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header: Vec<&str> = vec![
+            "schema_version",
+            "method",
+            "trials",
+            "seed",
+            "precision",
+            "max_trials",
+            "importance_sampling",
+            "flametype",
+            "level",
+            "item",
+            "base_att",
+            "noboss",
+            "stat",
+            "job",
+            "profile",
+            "formula",
+            "keep",
+        ];
+        header.extend(RESULT_COLUMNS);
+        header.extend(["rank", "score"]);
+        header.extend(FLAME_STATS);
+        writeln!(writer, "{}", header.join(","))?;
+
+        let settings = &self.settings;
+        let shared = |keep: f32, results: &Results| {
+            let mut shared = vec![
+                SCHEMA_VERSION.to_string(),
+                settings.method.to_owned(),
+                optional(settings.trials),
+                optional(settings.seed),
+                optional(settings.precision),
+                optional(settings.max_trials),
+                settings.importance_sampling.to_string(),
+                settings.flametype.clone(),
+                settings.level.clone(),
+                settings.item.clone(),
+                optional(settings.base_att),
+                settings.noboss.to_string(),
+                optional(settings.stat.as_ref()),
+                optional(settings.job.as_ref()),
+                optional(settings.profile.as_ref()),
+                settings.formula.clone(),
+                keep.to_string(),
+            ];
+            shared.extend(result_fields(results));
+            shared
+                .iter()
+                .map(|field| csv_field(field))
+                .collect::<Vec<String>>()
+        };
+
+        let empty = vec![String::new(); 2 + FLAME_STATS.len()].join(",");
+        for threshold in &self.thresholds {
+            let row = shared(threshold.keep, &threshold.results);
+            writeln!(writer, "{},{}", row.join(","), empty)?;
+        }
+        let shared = shared(settings.keep, &self.results);
+        if self.top.is_empty() {
+            // the highest threshold already has its row
+            if !self.thresholds.is_empty() {
+                return Ok(());
+            }
+            return writeln!(writer, "{},{}", shared.join(","), empty);
+        }
+        for flame in &self.top {
+            let mut row = shared.clone();
+            row.push(flame.rank.to_string());
+            row.push(flame.score.to_string());
+            row.extend(flame.lines.iter().map(|(_, value)| value.to_string()));
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Settings a sweep was produced with.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepSettings {
+    /// `simulation` or `exact`.
+    pub method: &'static str,
+    /// Seed of the random rolls, shared by every combination, `None` for exact results.
+    pub seed: Option<u64>,
+    pub precision: Option<f64>,
+    pub max_trials: Option<u64>,
+    pub importance_sampling: bool,
+    pub flametypes: Vec<String>,
+    pub levels: Vec<String>,
+    pub item: String,
+    pub base_att: Option<u16>,
+    pub noboss: bool,
+    pub stat: Option<String>,
+    pub job: Option<String>,
+    pub profile: Option<String>,
+    pub formula: String,
+    /// Every target, lowest first.
+    pub keep: Vec<f32>,
+    pub meso_per_nx: f64,
+}
+
+/// Outcome for one flame type, level bracket and target of a sweep.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepCell {
+    pub flametype: String,
+    pub level: String,
+    /// Amount of simulated flames, `None` for exact results.
+    pub trials: Option<u64>,
+    pub keep: f32,
+    /// Meso value of one flame, `None` if the flame type has no price.
+    pub flame_cost: Option<f64>,
+    #[serde(flatten)]
+    pub results: Results,
+}
+
+/// Settings and outcome of every combination of a sweep.
+#[derive(Debug, Clone, Serialize)]
+pub struct SweepReport {
+    pub schema_version: u32,
+    pub settings: SweepSettings,
+    /// One cell per flame type, level bracket and target, in that nesting order.
+    pub cells: Vec<SweepCell>,
+}
+
+impl SweepReport {
+    pub fn new(settings: SweepSettings, cells: Vec<SweepCell>) -> SweepReport {
+        SweepReport {
+            schema_version: SCHEMA_VERSION,
+            settings,
+            cells,
+        }
+    }
+}
+
+impl WriteReport for SweepReport {
+    /// Writes one JSON object per line: the settings, then every cell.
+    ///
+    /// Each object has a `type` field (`sweep_settings` or `cell`) and the `schema_version`.
+    fn write_ndjson<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        ndjson_line(&mut writer, "sweep_settings", &self.settings)?;
+        for cell in &self.cells {
+            ndjson_line(&mut writer, "cell", cell)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a CSV table with one row per cell, repeating the settings on every row.
+    fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{}", sweep_columns().join(","))?;
+        for cell in &self.cells {
+            let row: Vec<String> = sweep_fields(&self.settings, cell)
+                .iter()
+                .map(|field| csv_field(field))
+                .collect();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// CSV columns of a sweep cell, in the order [`sweep_fields`] writes them.
+fn sweep_columns() -> Vec<&'static str> {
+    let mut columns = vec![
+        "schema_version",
+        "method",
+        "seed",
+        "precision",
+        "max_trials",
+        "importance_sampling",
+        "item",
+        "base_att",
+        "noboss",
+        "stat",
+        "job",
+        "profile",
+        "formula",
+        "flametype",
+        "level",
+        "trials",
+        "keep",
+    ];
+    columns.extend(RESULT_COLUMNS);
+    columns
+}
+
+/// Unquoted CSV fields of a sweep cell, repeating the settings.
+fn sweep_fields(settings: &SweepSettings, cell: &SweepCell) -> Vec<String> {
+    let mut row = vec![
+        SCHEMA_VERSION.to_string(),
+        settings.method.to_owned(),
+        optional(settings.seed),
+        optional(settings.precision),
+        optional(settings.max_trials),
+        settings.importance_sampling.to_string(),
+        settings.item.clone(),
+        optional(settings.base_att),
+        settings.noboss.to_string(),
+        optional(settings.stat.as_ref()),
+        optional(settings.job.as_ref()),
+        optional(settings.profile.as_ref()),
+        settings.formula.clone(),
+        cell.flametype.clone(),
+        cell.level.clone(),
+        optional(cell.trials),
+        cell.keep.to_string(),
+    ];
+    row.extend(result_fields(&cell.results));
+    row
+}
+
+/// Percentiles of luck the advisor prices every flame type at: the cost half of all attempts
+/// stay under, and nine in ten.
+pub const LUCK_PERCENTILES: [f64; 2] = [50.0, 90.0];
+
+/// Flames and meso needed to reach a target in a percentile of attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct LuckCost {
+    pub percentile: f64,
+    /// `None` if the target is never reached.
+    pub flames: Option<u64>,
+    /// `None` if the target is never reached or the flame type has no price.
+    pub cost: Option<f64>,
+    /// Place of the flame type when ranked by this cost, `None` without a cost.
+    pub rank: Option<usize>,
+}
+
+/// One flame type in the advisor's ranking for a target.
+#[derive(Debug, Clone, Serialize)]
+pub struct Advice {
+    /// Place of the flame type when ranked by average cost, `None` without a cost.
+    pub rank: Option<usize>,
+    #[serde(flatten)]
+    pub cell: SweepCell,
+    /// Cost at every percentile of [`LUCK_PERCENTILES`].
+    pub luck: Vec<LuckCost>,
+}
+
+impl Advice {
+    /// Ranks the cells of one target by average cost and by the cost at every percentile of
+    /// luck. Flame types without a cost keep their order after the ranked ones.
+    pub fn rank(cells: Vec<SweepCell>) -> Vec<Advice> {
+        let mut ranking: Vec<Advice> = cells
+            .into_iter()
+            .map(|cell| {
+                let luck = LUCK_PERCENTILES
+                    .iter()
+                    .map(|percentile| {
+                        let flames = flames_for_luck(cell.results.probability, *percentile);
+                        LuckCost {
+                            percentile: *percentile,
+                            flames,
+                            cost: flames
+                                .zip(cell.flame_cost)
+                                .map(|(flames, cost)| flames as f64 * cost),
+                            rank: None,
+                        }
+                    })
+                    .collect();
+                Advice {
+                    rank: None,
+                    cell,
+                    luck,
+                }
+            })
+            .collect();
+
+        let places = |cost: &dyn Fn(&Advice) -> Option<f64>, ranking: &[Advice]| {
+            let mut order: Vec<usize> = (0..ranking.len())
+                .filter(|i| cost(&ranking[*i]).is_some())
+                .collect();
+            order.sort_by(|a, b| {
+                cost(&ranking[*a])
+                    .unwrap()
+                    .total_cmp(&cost(&ranking[*b]).unwrap())
+            });
+            order
+        };
+        for (place, i) in (1..).zip(places(&|advice| advice.cell.results.average_cost, &ranking)) {
+            ranking[i].rank = Some(place);
+        }
+        for l in 0..LUCK_PERCENTILES.len() {
+            for (place, i) in (1..).zip(places(&|advice| advice.luck[l].cost, &ranking)) {
+                ranking[i].luck[l].rank = Some(place);
+            }
+        }
+        // stable, so unranked flame types keep their order
+        ranking.sort_by_key(|advice| advice.rank.unwrap_or(usize::MAX));
+        ranking
+    }
+}
+
+/// Settings of an advisor run and its ranking of every flame type.
+#[derive(Debug, Clone, Serialize)]
+pub struct AdviceReport {
+    pub schema_version: u32,
+    pub settings: SweepSettings,
+    /// Every flame type for each target, lowest target first, cheapest flame type first.
+    pub ranking: Vec<Advice>,
+}
+
+impl AdviceReport {
+    pub fn new(settings: SweepSettings, ranking: Vec<Advice>) -> AdviceReport {
+        AdviceReport {
+            schema_version: SCHEMA_VERSION,
+            settings,
+            ranking,
+        }
+    }
+}
+
+impl WriteReport for AdviceReport {
+    /// Writes one JSON object per line: the settings, then every ranked flame type.
+    ///
+    /// Each object has a `type` field (`advice_settings` or `advice`) and the `schema_version`.
+    fn write_ndjson<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        ndjson_line(&mut writer, "advice_settings", &self.settings)?;
+        for advice in &self.ranking {
+            ndjson_line(&mut writer, "advice", advice)?;
+        }
+        Ok(())
+    }
+
+    /// Writes a CSV table with one row per ranked flame type, laid out like a sweep with the
+    /// ranks added. The cost at every percentile of luck is already among the sweep columns.
+    fn write_csv<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        let mut header: Vec<String> = sweep_columns().into_iter().map(str::to_owned).collect();
+        header.push("rank".to_owned());
+        for percentile in LUCK_PERCENTILES {
+            header.push(format!("p{}_rank", percentile));
+        }
+        writeln!(writer, "{}", header.join(","))?;
+
+        for advice in &self.ranking {
+            let mut row = sweep_fields(&self.settings, &advice.cell);
+            row.push(optional(advice.rank));
+            row.extend(advice.luck.iter().map(|luck| optional(luck.rank)));
+            let row: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+            writeln!(writer, "{}", row.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+/// Writes the odds of reaching a target within every amount of flames of a curve as CSV, one
+/// row per amount, for plotting.
+pub fn write_chance_csv<W: Write>(mut writer: W, curve: &[ChanceWithin]) -> io::Result<()> {
+    writeln!(
+        writer,
+        "flames,probability,probability_lower,probability_upper"
+    )?;
+    for chance in curve {
+        writeln!(
+            writer,
+            "{},{},{},{}",
+            chance.flames,
+            chance.probability,
+            lower(chance.interval),
+            upper(chance.interval)
+        )?;
+    }
+    writer.flush()
+}
+
+/// Writes a record as one line of JSON, tagged with its `type` and the `schema_version`.
+fn ndjson_line<W: Write, T: Serialize>(
+    writer: &mut W,
+    kind: &'static str,
+    record: &T,
+) -> io::Result<()> {
+    #[derive(Serialize)]
+    struct Record<'a, T: Serialize> {
+        #[serde(rename = "type")]
+        kind: &'static str,
+        schema_version: u32,
+        #[serde(flatten)]
+        record: &'a T,
+    }
+
+    let record = Record {
+        kind,
+        schema_version: SCHEMA_VERSION,
+        record,
+    };
+    serde_json::to_writer(&mut *writer, &record)?;
+    writeln!(writer)
+}
+
+/// CSV columns of [`Results`], in the order [`result_fields`] writes them.
+const RESULT_COLUMNS: [&str; 23] = [
+    "count",
+    "probability",
+    "probability_lower",
+    "probability_upper",
+    "average_flames",
+    "average_flames_lower",
+    "average_flames_upper",
+    "average_cost",
+    "average_cost_lower",
+    "average_cost_upper",
+    "chance_flames",
+    "chance_within",
+    "chance_within_lower",
+    "chance_within_upper",
+    "low_count",
+    "p50_flames",
+    "p50_cost",
+    "p75_flames",
+    "p75_cost",
+    "p90_flames",
+    "p90_cost",
+    "p99_flames",
+    "p99_cost",
+];
+
+fn result_fields(results: &Results) -> [String; 23] {
+    let chance = results.chance_within.as_ref();
+    let spend = |percentile: f64| {
+        results
+            .spend
+            .iter()
+            .find(|spend| spend.percentile == percentile)
+    };
+    let [p50, p75, p90, p99] = SPEND_PERCENTILES.map(spend);
+    [
+        optional(results.count),
+        results.probability.to_string(),
+        lower(results.probability_interval),
+        upper(results.probability_interval),
+        optional(results.average_flames),
+        lower(results.average_flames_interval),
+        upper(results.average_flames_interval),
+        optional(results.average_cost),
+        lower(results.average_cost_interval),
+        upper(results.average_cost_interval),
+        optional(chance.map(|chance| chance.flames)),
+        optional(chance.map(|chance| chance.probability)),
+        lower(chance.and_then(|chance| chance.interval)),
+        upper(chance.and_then(|chance| chance.interval)),
+        results.low_count.to_string(),
+        optional(p50.map(|spend| spend.flames)),
+        optional(p50.and_then(|spend| spend.cost)),
+        optional(p75.map(|spend| spend.flames)),
+        optional(p75.and_then(|spend| spend.cost)),
+        optional(p90.map(|spend| spend.flames)),
+        optional(p90.and_then(|spend| spend.cost)),
+        optional(p99.map(|spend| spend.flames)),
+        optional(p99.and_then(|spend| spend.cost)),
+    ]
+}
+
+fn optional<T: ToString>(value: Option<T>) -> String {
+    value.map_or_else(String::new, |value| value.to_string())
+}
+
+// unbounded bounds are left empty, like JSON writes them as null
+fn lower(interval: Option<Interval>) -> String {
+    optional(
+        interval
+            .map(|interval| interval.lower)
+            .filter(|bound| bound.is_finite()),
+    )
+}
+
+fn upper(interval: Option<Interval>) -> String {
+    optional(
+        interval
+            .map(|interval| interval.upper)
+            .filter(|bound| bound.is_finite()),
+    )
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tables::Price;
+
+    #[test]
+    fn flames_for_luck_follows_the_geometric_distribution() {
+        assert_eq!(flames_for_luck(0.01, 50.0), Some(69));
+        assert_eq!(flames_for_luck(0.01, 90.0), Some(230));
+        assert_eq!(flames_for_luck(0.01, 99.0), Some(459));
+        // a sure or nearly sure flame takes one try, and an impossible one never succeeds
+        assert_eq!(flames_for_luck(1.0, 99.0), Some(1));
+        assert_eq!(flames_for_luck(1.0 - 1e-12, 99.0), Some(1));
+        assert_eq!(flames_for_luck(0.0, 50.0), None);
+        // rare odds don't lose precision to rounding `1 - probability`
+        assert_eq!(flames_for_luck(1e-12, 50.0), Some(693_147_180_560));
+    }
+
+    #[test]
+    fn spend_interval_comes_from_the_odds_interval() {
+        let cost = FlameCost {
+            price: Price::Meso(1e6),
+            meso: 1e6,
+        };
+        let interval = Interval {
+            lower: 0.0,
+            upper: 0.02,
+        };
+        let spend = Spend::percentiles(Some(cost), 0.01, Some(interval));
+        assert_eq!(spend.len(), SPEND_PERCENTILES.len());
+        let p50 = spend[0];
+        assert_eq!(p50.flames, 69);
+        assert_eq!(p50.cost, Some(69e6));
+        // the likeliest odds need the fewest flames, and odds of 0 never get there
+        let flames = p50.flames_interval.unwrap();
+        assert_eq!(flames.lower, 35.0);
+        assert_eq!(flames.upper, f64::INFINITY);
+        assert_eq!(p50.cost_interval.unwrap().lower, 35e6);
+        assert!(Spend::percentiles(Some(cost), 0.0, None).is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+//! Precomputed sampling tables used to roll flames.
+//!
+//! The tables are built once per simulator and only read while rolling, so every thread can
+//! share them without copying or re-normalising weights on each draw.
+
+use crate::flame::{add_stats, line_stats, Stats, FLAME_STATS};
+use crate::tables::Item;
+use rand::Rng;
+
+/// Most lines a single flame can roll.
+pub(crate) const MAX_LINES: usize = 4;
+
+/// Draws an index with the given weights in constant time (Vose's alias method).
+#[derive(Clone, Debug)]
+pub(crate) struct AliasTable {
+    /// Odds of keeping the drawn column instead of taking its alias.
+    keep: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    /// Builds a table from non-negative weights, at least one of which is above 0.
+    pub(crate) fn new<W: Copy + Into<f64>>(weights: &[W]) -> AliasTable {
+        let weights: Vec<f64> = weights.iter().map(|w| (*w).into()).collect();
+        let total: f64 = weights.iter().sum();
+        let columns = weights.len();
+        let mut scaled: Vec<f64> = weights.iter().map(|w| w * columns as f64 / total).collect();
+        let mut keep = vec![1.0; columns];
+        let mut alias: Vec<usize> = (0..columns).collect();
+
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..columns).partition(|i| scaled[*i] < 1.0);
+        while let (Some(&less), Some(&more)) = (small.last(), large.last()) {
+            small.pop();
+            keep[less] = scaled[less];
+            alias[less] = more;
+            scaled[more] -= 1.0 - scaled[less];
+            if scaled[more] < 1.0 {
+                large.pop();
+                small.push(more);
+            }
+        }
+        // whatever is left only misses 1.0 through rounding, unless its weight is 0
+        for i in small {
+            if weights[i] <= 0.0 {
+                keep[i] = 0.0;
+            }
+        }
+
+        AliasTable { keep, alias }
+    }
+
+    pub(crate) fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> usize {
+        // one draw picks the column with its whole part and decides on the alias with the rest
+        let draw = rng.gen::<f64>() * self.keep.len() as f64;
+        let column = (draw as usize).min(self.keep.len() - 1);
+        if draw - (column as f64) < self.keep[column] {
+            column
+        } else {
+            self.alias[column]
+        }
+    }
+}
+
+/// A rollable line, with the stats it adds to and its value for every tier.
+#[derive(Clone, Debug)]
+struct Line {
+    stats: &'static [usize],
+    values: Vec<u16>,
+}
+
+/// Line, tier and line count tables of a flame simulator.
+#[derive(Clone, Debug)]
+pub(crate) struct FlameSampler {
+    lines: Vec<Line>,
+    tiers: AliasTable,
+    /// `None` for boss flames, which always roll [`MAX_LINES`] lines.
+    line_counts: Option<(Vec<usize>, AliasTable)>,
+}
+
+impl FlameSampler {
+    /// `line_counts` pairs each possible amount of lines with its weight.
+    pub(crate) fn new(
+        option_table: &[Item<u16>],
+        tier_weights: &[f32],
+        line_counts: Option<(&[usize], &[f32])>,
+    ) -> FlameSampler {
+        FlameSampler {
+            lines: option_table
+                .iter()
+                .map(|option| Line {
+                    stats: line_stats(option.n),
+                    values: option.v.clone(),
+                })
+                .collect(),
+            tiers: AliasTable::new(tier_weights),
+            line_counts: line_counts
+                .map(|(counts, weights)| (counts.to_vec(), AliasTable::new(weights))),
+        }
+    }
+
+    /// Rolls the stat totals of a flame.
+    pub(crate) fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> Stats {
+        let mut stats = [0; FLAME_STATS.len()];
+        let count = match &self.line_counts {
+            Some((counts, table)) => counts[table.sample(rng)],
+            None => MAX_LINES,
+        }
+        .min(self.lines.len());
+
+        // redrawing repeats picks every subset with equal odds, like a partial shuffle
+        let mut picked = [0; MAX_LINES];
+        for i in 0..count {
+            let option = loop {
+                let option = rng.gen_range(0..self.lines.len());
+                if !picked[..i].contains(&option) {
+                    break option;
+                }
+            };
+            picked[i] = option;
+
+            let line = &self.lines[option];
+            add_stats(&mut stats, line.stats, line.values[self.tiers.sample(rng)]);
+        }
+        stats
+    }
+}
+
+/// Largest tilt tried when aiming at a target, reached when the target is out of reach.
+const MAX_TILT: f64 = 1024.0;
+
+/// Share of the plain odds mixed into every tilted distribution, which keeps outcomes the tilt
+/// nearly rules out possible and caps their importance weights.
+const PLAIN_SHARE: f64 = 0.1;
+
+/// Flame sampler biased toward high scores, to estimate the odds of rare targets.
+///
+/// Tier weights are tilted exponentially toward higher scoring tiers, `w * exp(tilt * score)`,
+/// lines are picked one at a time in proportion to their total tilted weight, and non-boss
+/// flames roll more lines. Each flame comes with its importance weight, its odds under
+/// [`FlameSampler`] divided by its odds here, which keeps weighted counts unbiased.
+#[derive(Clone, Debug)]
+pub(crate) struct TiltedSampler {
+    lines: Vec<TiltedLine>,
+    /// Odds of picking each line first.
+    picks: Vec<f64>,
+    counts: Vec<usize>,
+    count_table: AliasTable,
+    /// `ln(plain odds / tilted odds)` of every amount of lines.
+    count_log_ratios: Vec<f64>,
+    /// `ln` of the amount of distinct line sets, for every amount of lines.
+    log_line_sets: [f64; MAX_LINES + 1],
+}
+
+#[derive(Clone, Debug)]
+struct TiltedLine {
+    stats: &'static [usize],
+    values: Vec<u16>,
+    tiers: AliasTable,
+    /// `ln(plain odds / tilted odds)` of every tier.
+    log_ratios: Vec<f64>,
+}
+
+impl TiltedSampler {
+    /// `scores` holds the score of every tier of every line in `option_table`. The tilt is
+    /// chosen so the average flame scores about `target`, or left at 0 if it already does.
+    pub(crate) fn new(
+        option_table: &[Item<u16>],
+        tier_weights: &[f32],
+        line_counts: Option<(&[usize], &[f32])>,
+        scores: &[Vec<f64>],
+        target: f64,
+    ) -> TiltedSampler {
+        let plain = Odds::plain(tier_weights, line_counts, option_table.len());
+        let tilted = plain.tilt(scores, find_tilt(&plain, scores, target));
+
+        let lines = option_table
+            .iter()
+            .zip(&tilted.tiers)
+            .map(|(option, tiers)| TiltedLine {
+                stats: line_stats(option.n),
+                values: option.v.clone(),
+                tiers: AliasTable::new(tiers),
+                log_ratios: log_ratios(&plain.tiers[0], tiers),
+            })
+            .collect();
+
+        let mut log_line_sets = [0.0; MAX_LINES + 1];
+        for (count, log_sets) in log_line_sets.iter_mut().enumerate() {
+            *log_sets = (0..count.min(option_table.len()))
+                .map(|i| ((option_table.len() - i) as f64 / (i + 1) as f64).ln())
+                .sum();
+        }
+
+        TiltedSampler {
+            lines,
+            counts: plain.counts.iter().map(|(count, _)| *count).collect(),
+            count_table: AliasTable::new(&tilted.count_odds()),
+            count_log_ratios: log_ratios(&plain.count_odds(), &tilted.count_odds()),
+            picks: tilted.picks,
+            log_line_sets,
+        }
+    }
+
+    /// Rolls the stat totals of a flame, along with its importance weight.
+    pub(crate) fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> (Stats, f64) {
+        let mut stats = [0; FLAME_STATS.len()];
+        let index = self.count_table.sample(rng);
+        let count = self.counts[index];
+
+        // every line set is equally likely without the tilt
+        let mut log_weight = self.count_log_ratios[index] - self.log_line_sets[count];
+        let mut picked = [0; MAX_LINES];
+        let mut remaining = 1.0;
+        for i in 0..count {
+            let mut draw = rng.gen::<f64>() * remaining;
+            let mut option = 0;
+            for (j, odds) in self.picks.iter().enumerate() {
+                if picked[..i].contains(&j) {
+                    continue;
+                }
+                // rounding can run past the last line, which then keeps it
+                option = j;
+                if draw < *odds {
+                    break;
+                }
+                draw -= odds;
+            }
+            picked[i] = option;
+            remaining -= self.picks[option];
+
+            let line = &self.lines[option];
+            let tier = line.tiers.sample(rng);
+            log_weight += line.log_ratios[tier];
+            add_stats(&mut stats, line.stats, line.values[tier]);
+        }
+
+        log_weight -= self.pick_odds(&picked[..count], 1.0).ln();
+        (stats, log_weight.exp())
+    }
+
+    /// Odds of picking exactly these lines, in any order, from lines with `remaining` odds.
+    fn pick_odds(&self, picked: &[usize], remaining: f64) -> f64 {
+        let mut odds = 0.0;
+        for (i, line) in picked.iter().enumerate() {
+            let mut rest = [0; MAX_LINES];
+            rest[..i].copy_from_slice(&picked[..i]);
+            rest[i..picked.len() - 1].copy_from_slice(&picked[i + 1..]);
+            let pick = self.picks[*line];
+            odds += pick / remaining * self.pick_odds(&rest[..picked.len() - 1], remaining - pick);
+        }
+        if picked.is_empty() {
+            1.0
+        } else {
+            odds
+        }
+    }
+}
+
+/// `ln(plain / tilted)` of every outcome, 0 for outcomes that can't happen.
+fn log_ratios(plain: &[f64], tilted: &[f64]) -> Vec<f64> {
+    plain
+        .iter()
+        .zip(tilted)
+        .map(|(p, q)| if *p > 0.0 { (p / q).ln() } else { 0.0 })
+        .collect()
+}
+
+/// Odds of every tier of every line, of picking every line and of every amount of lines.
+struct Odds {
+    tiers: Vec<Vec<f64>>,
+    picks: Vec<f64>,
+    /// Amounts of lines, capped at the amount of options, with their odds.
+    counts: Vec<(usize, f64)>,
+}
+
+impl Odds {
+    /// Untilted odds, with every line picked equally often.
+    fn plain(
+        tier_weights: &[f32],
+        line_counts: Option<(&[usize], &[f32])>,
+        options: usize,
+    ) -> Odds {
+        let total: f64 = tier_weights.iter().map(|w| *w as f64).sum();
+        let tiers = tier_weights.iter().map(|w| *w as f64 / total).collect();
+        let counts = match line_counts {
+            Some((counts, weights)) => {
+                let total: f64 = weights.iter().map(|w| *w as f64).sum();
+                counts
+                    .iter()
+                    .zip(weights)
+                    .map(|(count, weight)| ((*count).min(options), *weight as f64 / total))
+                    .collect()
+            }
+            None => vec![(MAX_LINES.min(options), 1.0)],
+        };
+        Odds {
+            tiers: vec![tiers; options],
+            picks: vec![1.0 / options as f64; options],
+            counts,
+        }
+    }
+
+    fn count_odds(&self) -> Vec<f64> {
+        self.counts.iter().map(|(_, odds)| *odds).collect()
+    }
+
+    /// Tilts plain odds toward higher `scores` and mixes the plain odds back in.
+    fn tilt(&self, scores: &[Vec<f64>], tilt: f64) -> Odds {
+        let mix = |tilted: f64, plain: f64| (1.0 - PLAIN_SHARE) * tilted + PLAIN_SHARE * plain;
+
+        // ln of each line's total tilted weight, the plain tier odds adding up to 1
+        let mut log_totals = Vec::new();
+        let mut tiers = Vec::new();
+        for (plain, scores) in self.tiers.iter().zip(scores) {
+            let exponents: Vec<f64> = plain
+                .iter()
+                .zip(scores)
+                .map(|(p, score)| p.ln() + tilt * score)
+                .collect();
+            let log_total = log_sum_exp(&exponents);
+            tiers.push(
+                exponents
+                    .iter()
+                    .zip(plain)
+                    .map(|(e, p)| mix((e - log_total).exp(), *p))
+                    .collect(),
+            );
+            log_totals.push(log_total);
+        }
+
+        // lines are picked in proportion to their total tilted weight
+        let log_sum = log_sum_exp(&log_totals);
+        let log_mean = log_sum - (log_totals.len() as f64).ln();
+        let picks = log_totals
+            .iter()
+            .zip(&self.picks)
+            .map(|(log_total, p)| mix((log_total - log_sum).exp(), *p))
+            .collect();
+
+        // a set of lines weighs about the average line weight to the power of its size
+        let exponents: Vec<f64> = self
+            .counts
+            .iter()
+            .map(|(count, p)| p.ln() + *count as f64 * log_mean)
+            .collect();
+        let log_total = log_sum_exp(&exponents);
+        let counts = self
+            .counts
+            .iter()
+            .zip(&exponents)
+            .map(|((count, p), e)| (*count, mix((e - log_total).exp(), *p)))
+            .collect();
+
+        Odds {
+            tiers,
+            picks,
+            counts,
+        }
+    }
+
+    /// Approximate average score, treating lines as picked with replacement.
+    fn average_score(&self, scores: &[Vec<f64>]) -> f64 {
+        let line: f64 = self
+            .tiers
+            .iter()
+            .zip(&self.picks)
+            .zip(scores)
+            .map(|((tiers, pick), scores)| {
+                pick * tiers.iter().zip(scores).map(|(q, s)| q * s).sum::<f64>()
+            })
+            .sum();
+        let lines: f64 = self
+            .counts
+            .iter()
+            .map(|(count, odds)| *count as f64 * odds)
+            .sum();
+        line * lines
+    }
+}
+
+/// `ln(sum(exp(x)))` of the exponents, without overflowing.
+fn log_sum_exp(exponents: &[f64]) -> f64 {
+    let most = exponents.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+    if most == f64::NEG_INFINITY {
+        return most;
+    }
+    most + exponents.iter().map(|e| (e - most).exp()).sum::<f64>().ln()
+}
+
+/// Finds the tilt that makes the average flame score about `target`.
+fn find_tilt(plain: &Odds, scores: &[Vec<f64>], target: f64) -> f64 {
+    let average = |tilt: f64| plain.tilt(scores, tilt).average_score(scores);
+    if average(0.0) >= target {
+        return 0.0;
+    }
+    let mut high = 1.0 / 1024.0;
+    while average(high) < target {
+        if high >= MAX_TILT {
+            return MAX_TILT;
+        }
+        high *= 2.0;
+    }
+    let mut low = 0.0;
+    for _ in 0..64 {
+        let middle = (low + high) / 2.0;
+        if average(middle) < target {
+            low = middle;
+        } else {
+            high = middle;
+        }
+    }
+    high
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AliasTable, FlameSampler};
+    use crate::fixtures::eflame_simulator;
+    use crate::tables::Item;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+
+    /// Checks every outcome was drawn within 5 standard deviations of its expected count.
+    fn assert_frequencies(counts: &[u64], odds: &[f64]) {
+        let draws: u64 = counts.iter().sum();
+        for (i, (count, p)) in counts.iter().zip(odds).enumerate() {
+            let expected = draws as f64 * p;
+            let deviation = (draws as f64 * p * (1.0 - p)).sqrt();
+            assert!(
+                (*count as f64 - expected).abs() <= 5.0 * deviation,
+                "outcome {}: {} draws instead of about {}",
+                i,
+                count,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn alias_table_reproduces_weights() {
+        let weights = [0.5f32, 0.0, 3.0, 1.5, 2.0, 0.25];
+        let table = AliasTable::new(&weights);
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut counts = [0; 6];
+        for _ in 0..1_000_000 {
+            counts[table.sample(&mut rng)] += 1;
+        }
+        assert_eq!(counts[1], 0, "a weight of 0 was drawn");
+        let total: f32 = weights.iter().sum();
+        let odds: Vec<f64> = weights.iter().map(|w| (w / total) as f64).collect();
+        assert_frequencies(&counts, &odds);
+    }
+
+    #[test]
+    fn lines_are_picked_with_equal_odds() {
+        // five single-tier lines adding 1 to distinct stats, two of them picked per flame
+        let option_table: Vec<Item<u16>> = ["str", "dex", "int", "luk", "att"]
+            .into_iter()
+            .map(|n| Item { n, v: vec![1] })
+            .collect();
+        let sampler = FlameSampler::new(&option_table, &[1.0], Some((&[2], &[1.0])));
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let mut counts = [0; 32];
+        for _ in 0..200_000 {
+            let stats = sampler.roll(&mut rng);
+            let subset = (0..5).fold(0, |subset, i| subset | (stats[i] as usize) << i);
+            counts[subset] += 1;
+        }
+        // the 10 pairs of distinct lines, and nothing else
+        let odds: Vec<f64> = (0..32)
+            .map(
+                |subset: usize| {
+                    if subset.count_ones() == 2 {
+                        0.1
+                    } else {
+                        0.0
+                    }
+                },
+            )
+            .collect();
+        assert_frequencies(&counts, &odds);
+    }
+
+    #[test]
+    fn tilted_estimate_matches_exact() {
+        // targets about 1 flame in 10,000 reaches, which plain sampling would barely hit
+        for (noboss, keep) in [(false, 180.0), (true, 100.0)] {
+            let simulator = eflame_simulator(noboss);
+            let exact = simulator
+                .exact_distribution()
+                .probability_at_least(keep as f64);
+            let interval = simulator
+                .with_importance_sampling(keep)
+                .run(100_000, keep, 0, 1, |_| {})
+                .probability_interval();
+            assert!(
+                interval.lower <= exact && exact <= interval.upper,
+                "noboss {}: exact {} outside {:?}",
+                noboss,
+                exact,
+                interval
+            );
+        }
+    }
+}
@@ -0,0 +1,437 @@
+//! Flame scoring: stat equivalence weights, built-in class presets and user-defined formulas.
+//!
+//! A score formula is a weighted sum of the stat totals on a flame, such as
+//! `luk + 0.1*dex + 3*att + 10*allstat`. Formulas are kept linear so a flame's score is the sum
+//! of its lines' scores, which the exact distribution relies on.
+
+use crate::flame::{Flame, Stats, FLAME_STATS};
+use crate::types::JobStat;
+use config::{Config, ConfigError, File, FileFormat, ValueKind};
+use std::fmt;
+
+#[derive(Debug)]
+pub enum ScoreError {
+    Config(ConfigError),
+    UnknownStat(String),
+    InvalidFormula { formula: String, reason: String },
+    UnknownProfile(String),
+}
+
+impl fmt::Display for ScoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScoreError::Config(e) => write!(f, "{}", e),
+            ScoreError::UnknownStat(stat) => write!(
+                f,
+                "unknown stat '{}' [options: {}, allstat]",
+                stat,
+                FLAME_STATS.join(", ")
+            ),
+            ScoreError::InvalidFormula { formula, reason } => {
+                write!(f, "invalid score formula '{}': {}", formula, reason)
+            }
+            ScoreError::UnknownProfile(name) => write!(f, "unknown score profile: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ScoreError {}
+
+impl From<ConfigError> for ScoreError {
+    fn from(e: ConfigError) -> ScoreError {
+        ScoreError::Config(e)
+    }
+}
+
+/// Stat equivalence ratios used by the built-in presets, as found in `flame_values.json`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreWeights {
+    pub allstat: f32,
+    pub allstat_x: f32, // xenon
+    pub substat: f32,
+    pub att: f32,
+    pub att_d: f32, // da
+    pub att_x: f32, // xenon
+    pub hpmp: f32,  // kanna
+    pub boss: f32,  // weapon boss damage %
+    pub dmg: f32,   // weapon damage %
+    pub lvlred: f32,
+    pub def: f32,
+    pub speed: f32,
+    pub jmp: f32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> ScoreWeights {
+        ScoreWeights {
+            allstat: 8.0,
+            allstat_x: 20.0,
+            substat: 0.10,
+            att: 3.0,
+            att_d: 20.0,
+            att_x: 6.0,
+            hpmp: 120.0,
+            boss: 10.0,
+            dmg: 10.0,
+            lvlred: 0.0,
+            def: 0.0,
+            speed: 0.0,
+            jmp: 0.0,
+        }
+    }
+}
+
+impl ScoreWeights {
+    /// Reads the weights from a `flame_values.json` file.
+    ///
+    /// The weapon and utility line weights are optional so older files keep working.
+    pub fn load(path: &str) -> Result<ScoreWeights, ConfigError> {
+        let config = Config::builder()
+            .add_source(File::new(path, FileFormat::Json))
+            .build()?;
+        let defaults = ScoreWeights::default();
+
+        Ok(ScoreWeights {
+            allstat: config.get_float("allstat")? as f32,
+            allstat_x: config.get_float("allstat_x")? as f32,
+            substat: config.get_float("substat")? as f32,
+            att: config.get_float("att")? as f32,
+            att_d: config.get_float("att_d")? as f32,
+            att_x: config.get_float("att_x")? as f32,
+            hpmp: config.get_float("hpmp")? as f32,
+            boss: config.get_float("boss").map_or(defaults.boss, |v| v as f32),
+            dmg: config.get_float("dmg").map_or(defaults.dmg, |v| v as f32),
+            lvlred: config
+                .get_float("lvlred")
+                .map_or(defaults.lvlred, |v| v as f32),
+            def: config.get_float("def").map_or(defaults.def, |v| v as f32),
+            speed: config
+                .get_float("speed")
+                .map_or(defaults.speed, |v| v as f32),
+            jmp: config.get_float("jmp").map_or(defaults.jmp, |v| v as f32),
+        })
+    }
+}
+
+/// Returns the position of a stat in [`FLAME_STATS`], accepting `allstat` for all stat %.
+fn stat_index(name: &str) -> Result<usize, ScoreError> {
+    let name = match name {
+        "allstat" => "as",
+        _ => name,
+    };
+    FLAME_STATS
+        .iter()
+        .position(|stat| *stat == name)
+        .ok_or_else(|| ScoreError::UnknownStat(name.to_owned()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Stat(String),
+    Plus,
+    Minus,
+    Times,
+    Divide,
+}
+
+fn tokenize(formula: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = formula.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            ' ' | '\t' => {}
+            '+' => tokens.push(Token::Plus),
+            '-' => tokens.push(Token::Minus),
+            '*' => tokens.push(Token::Times),
+            '/' => tokens.push(Token::Divide),
+            '0'..='9' | '.' => {
+                let mut number = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_digit() || *c == '.') {
+                    number.push(c);
+                }
+                let value = number
+                    .parse()
+                    .map_err(|_| format!("'{}' is not a number", number))?;
+                tokens.push(Token::Number(value));
+            }
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut name = c.to_string();
+                while let Some(c) = chars.next_if(|c| c.is_ascii_alphanumeric() || *c == '_') {
+                    name.push(c);
+                }
+                tokens.push(Token::Stat(name.to_lowercase()));
+            }
+            c => return Err(format!("unexpected character '{}'", c)),
+        }
+    }
+    Ok(tokens)
+}
+
+/// A linear score formula: one coefficient per stat in [`FLAME_STATS`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoreFormula {
+    coefficients: Vec<f32>,
+}
+
+impl ScoreFormula {
+    /// Builds a formula from `(stat, coefficient)` pairs. Repeated stats add up.
+    pub fn from_coefficients<'a, I>(coefficients: I) -> Result<ScoreFormula, ScoreError>
+    where
+        I: IntoIterator<Item = (&'a str, f32)>,
+    {
+        let mut formula = ScoreFormula {
+            coefficients: vec![0.0; FLAME_STATS.len()],
+        };
+        for (stat, coefficient) in coefficients {
+            formula.coefficients[stat_index(stat)?] += coefficient;
+        }
+        Ok(formula)
+    }
+
+    /// Parses a formula such as `luk + 0.1*dex + 0.1*str + 3*att + 10*allstat`.
+    ///
+    /// Each term is a stat optionally multiplied or divided by numbers, e.g. `hp/120`.
+    pub fn parse(formula: &str) -> Result<ScoreFormula, ScoreError> {
+        let invalid = |reason: String| ScoreError::InvalidFormula {
+            formula: formula.to_owned(),
+            reason,
+        };
+        let tokens = tokenize(formula).map_err(invalid)?;
+        let mut terms: Vec<(String, f32)> = Vec::new();
+        let mut tokens = tokens.into_iter().peekable();
+
+        while tokens.peek().is_some() {
+            let mut coefficient = 1.0;
+            while let Some(sign) = tokens.next_if(|t| matches!(t, Token::Plus | Token::Minus)) {
+                if sign == Token::Minus {
+                    coefficient = -coefficient;
+                }
+            }
+
+            let mut stat = None;
+            let mut operator = Token::Times;
+            loop {
+                match (tokens.next(), &operator) {
+                    (Some(Token::Number(n)), Token::Times) => coefficient *= n,
+                    (Some(Token::Number(n)), Token::Divide) if n != 0.0 => coefficient /= n,
+                    (Some(Token::Number(_)), _) => return Err(invalid("division by zero".into())),
+                    (Some(Token::Stat(name)), Token::Times) if stat.is_none() => stat = Some(name),
+                    (Some(Token::Stat(name)), Token::Times) => {
+                        return Err(invalid(format!(
+                            "'{}' multiplies two stats, scores must be a weighted sum of stats",
+                            name
+                        )))
+                    }
+                    (Some(Token::Stat(name)), _) => {
+                        return Err(invalid(format!("can't divide by the stat '{}'", name)))
+                    }
+                    _ => return Err(invalid("expected a number or stat".into())),
+                }
+
+                match tokens.next_if(|t| matches!(t, Token::Times | Token::Divide)) {
+                    Some(next) => operator = next,
+                    None => break,
+                }
+            }
+
+            match stat {
+                Some(stat) => terms.push((stat, coefficient)),
+                None => return Err(invalid("every term needs a stat".into())),
+            }
+            if tokens
+                .peek()
+                .is_some_and(|t| !matches!(t, Token::Plus | Token::Minus))
+            {
+                return Err(invalid("expected '+' or '-' between terms".into()));
+            }
+        }
+
+        if terms.is_empty() {
+            return Err(invalid("the formula is empty".into()));
+        }
+        ScoreFormula::from_coefficients(terms.iter().map(|(stat, c)| (stat.as_str(), *c)))
+    }
+
+    /// Built-in formula for a class, using the given stat equivalence weights.
+    pub fn preset(stat: JobStat, weights: &ScoreWeights) -> ScoreFormula {
+        let w = weights;
+        let mut terms = match stat {
+            JobStat::Str => vec![("str", 1.0), ("dex", w.substat), ("att", w.att)],
+            JobStat::Dex => vec![("dex", 1.0), ("str", w.substat), ("att", w.att)],
+            JobStat::Int => vec![("int", 1.0), ("luk", w.substat), ("matt", w.att)],
+            JobStat::Luk => vec![("luk", 1.0), ("dex", w.substat), ("att", w.att)],
+            JobStat::Kanna => vec![
+                ("int", 1.0),
+                ("luk", w.substat),
+                ("matt", w.att),
+                ("hp", 1.0 / w.hpmp),
+                ("mp", 1.0 / w.hpmp),
+            ],
+            JobStat::Da => vec![("hp", 1.0), ("str", w.substat), ("att", w.att_d)],
+            JobStat::Xenon => vec![("str", 1.0), ("dex", 1.0), ("luk", 1.0), ("att", w.att_x)],
+            JobStat::AltThief => vec![
+                ("luk", 1.0),
+                ("str", w.substat),
+                ("dex", w.substat),
+                ("att", w.att),
+            ],
+        };
+        let allstat = match stat {
+            JobStat::Xenon => w.allstat_x,
+            _ => w.allstat,
+        };
+        terms.extend([
+            ("as", allstat),
+            // boss damage and damage only roll on weapons
+            ("boss", w.boss),
+            ("dmg", w.dmg),
+            ("lvlred", w.lvlred),
+            ("def", w.def),
+            ("speed", w.speed),
+            ("jmp", w.jmp),
+        ]);
+        ScoreFormula::from_coefficients(terms).expect("presets only use known stats")
+    }
+
+    /// Coefficient of a stat, or 0 if the stat isn't known.
+    pub fn coefficient(&self, stat: &str) -> f32 {
+        stat_index(stat).map_or(0.0, |i| self.coefficients[i])
+    }
+
+    pub fn score(&self, flame: &Flame) -> f32 {
+        flame
+            .lines
+            .iter()
+            .zip(self.coefficients.iter())
+            .map(|(line, coefficient)| line.1 as f32 * coefficient)
+            .sum()
+    }
+
+    /// Scores rolled stat totals the same way [`score`](Self::score) scores a flame.
+    pub(crate) fn score_stats(&self, stats: &Stats) -> f32 {
+        stats
+            .iter()
+            .zip(self.coefficients.iter())
+            .map(|(value, coefficient)| *value as f32 * coefficient)
+            .sum()
+    }
+}
+
+impl fmt::Display for ScoreFormula {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for (stat, coefficient) in FLAME_STATS.iter().zip(self.coefficients.iter()) {
+            if *coefficient == 0.0 {
+                continue;
+            }
+            let sign = if *coefficient < 0.0 { "-" } else { "+" };
+            match (first, sign) {
+                (true, "+") => {}
+                (true, _) => write!(f, "-")?,
+                (false, _) => write!(f, " {} ", sign)?,
+            }
+            first = false;
+            let magnitude = coefficient.abs();
+            if magnitude == 1.0 {
+                write!(f, "{}", stat)?;
+            } else {
+                write!(f, "{}*{}", magnitude, stat)?;
+            }
+        }
+        if first {
+            write!(f, "0")?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a custom score profile from the `profiles` object of a `flame_values.json` file,
+/// or `None` if there's no profile with that name.
+///
+/// Each profile is either a formula string or a map of stat coefficients:
+///
+/// ```json
+/// "profiles": {
+///     "bishop": "int + 0.1*luk + 3*matt + 10*allstat",
+///     "night_lord": { "luk": 1, "dex": 0.1, "str": 0.1, "att": 3, "allstat": 10 }
+/// }
+/// ```
+pub fn load_profile(path: &str, name: &str) -> Result<Option<ScoreFormula>, ScoreError> {
+    let config = Config::builder()
+        .add_source(File::new(path, FileFormat::Json))
+        .build()?;
+    let profiles = match config.get_table("profiles") {
+        Ok(profiles) => profiles,
+        Err(ConfigError::NotFound(_)) => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let value = match profiles
+        .into_iter()
+        .find(|(profile, _)| profile.eq_ignore_ascii_case(name))
+    {
+        Some((_, value)) => value,
+        None => return Ok(None),
+    };
+
+    let formula = match value.kind {
+        ValueKind::Table(coefficients) => {
+            let mut terms = Vec::new();
+            for (stat, coefficient) in coefficients {
+                terms.push((stat, coefficient.into_float()? as f32));
+            }
+            ScoreFormula::from_coefficients(terms.iter().map(|(s, c)| (s.as_str(), *c)))?
+        }
+        _ => ScoreFormula::parse(&value.into_string()?)?,
+    };
+    Ok(Some(formula))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_invalid(formula: &str) -> bool {
+        matches!(
+            ScoreFormula::parse(formula),
+            Err(ScoreError::InvalidFormula { .. })
+        )
+    }
+
+    #[test]
+    fn parses_division() {
+        let formula = ScoreFormula::parse("hp/120").unwrap();
+        assert!((formula.coefficient("hp") - 1.0 / 120.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn parses_unary_minus() {
+        let formula = ScoreFormula::parse("-def + luk - 2*dex").unwrap();
+        assert_eq!(formula.coefficient("def"), -1.0);
+        assert_eq!(formula.coefficient("luk"), 1.0);
+        assert_eq!(formula.coefficient("dex"), -2.0);
+    }
+
+    #[test]
+    fn rejects_stat_products() {
+        assert!(is_invalid("luk*dex"));
+    }
+
+    #[test]
+    fn rejects_division_by_zero() {
+        assert!(is_invalid("luk/0"));
+    }
+
+    #[test]
+    fn allstat_is_as() {
+        assert_eq!(
+            ScoreFormula::parse("10*allstat").unwrap(),
+            ScoreFormula::parse("10*as").unwrap()
+        );
+        assert_eq!(
+            ScoreFormula::parse("allstat").unwrap().coefficient("as"),
+            1.0
+        );
+    }
+}
@@ -0,0 +1,629 @@
+//! Flame simulation: rolling single flames and aggregating many of them.
+
+use crate::confidence::{self, Interval};
+use crate::exact::{self, ScoreDistribution};
+use crate::flame::{add_line, Flame, Stats, FLAME_STATS};
+use crate::sampler::{FlameSampler, TiltedSampler};
+use crate::score::ScoreFormula;
+use crate::tables::{GameTables, Item};
+use crate::types::{EquipLevel, FlameType, LevelBracket};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::prelude::*;
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::{BinaryHeap, HashMap},
+    fmt,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Error {
+    MissingBaseAttack,
+    /// A weapon was given a level bracket, but its attack lines depend on the exact level.
+    MissingWeaponLevel(LevelBracket),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MissingBaseAttack => write!(f, "weapon flames require a base attack above 0"),
+            Error::MissingWeaponLevel(bracket) => write!(
+                f,
+                "weapon attack lines grow every 40 equip levels, give the weapon's level \
+                 instead of the bracket {}",
+                bracket
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Aggregate results of a simulation run.
+#[derive(Debug, Clone)]
+pub struct SimulationResult {
+    pub trials: u64,
+    /// Amount of flames scoring at least the `keep` threshold.
+    pub count: u64,
+    /// Importance weights of those flames, if the rolls were biased toward the threshold.
+    pub weights: Option<Weights>,
+    /// Best flames, highest score first.
+    pub top: Vec<Flame>,
+    /// Score distribution of every flame, if it was collected.
+    pub distribution: Option<ScoreDistribution>,
+    /// Counts for the extra thresholds, in the order they were given.
+    pub thresholds: Vec<ThresholdCount>,
+}
+
+/// Flames that reached one of the extra thresholds of
+/// [`FlameSimulator::with_thresholds`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThresholdCount {
+    pub keep: f32,
+    pub count: u64,
+    pub weights: Option<Weights>,
+}
+
+/// Sums of the importance weights of the flames that reached the threshold.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Weights {
+    pub sum: f64,
+    pub sum_sq: f64,
+}
+
+impl SimulationResult {
+    /// Fraction of flames that reached the threshold, or its unbiased estimate from weighted
+    /// flames.
+    pub fn probability(&self) -> f64 {
+        if self.trials == 0 {
+            return 0.0;
+        }
+        match self.weights {
+            Some(weights) => weights.sum / self.trials as f64,
+            None => self.count as f64 / self.trials as f64,
+        }
+    }
+
+    /// Average amount of flames needed to reach the threshold, or 0 if it was never reached.
+    pub fn average_flames(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        1.0 / self.probability()
+    }
+
+    /// Odds of reaching the threshold at least once within `flames` flames.
+    pub fn chance_within(&self, flames: i32) -> f64 {
+        chance_within(self.probability(), flames)
+    }
+
+    /// Interval of [`probability`](Self::probability): the Wilson interval, or a normal
+    /// interval from the spread of the weights of weighted flames.
+    pub fn probability_interval(&self) -> Interval {
+        match self.weights {
+            Some(_) if self.count == 0 => Interval {
+                lower: 0.0,
+                upper: 1.0,
+            },
+            Some(weights) => confidence::weighted(weights.sum, weights.sum_sq, self.trials),
+            None => confidence::wilson(self.count, self.trials),
+        }
+    }
+
+    /// Interval of [`average_flames`](Self::average_flames); the upper bound is infinite while
+    /// the threshold could still be out of reach.
+    pub fn average_flames_interval(&self) -> Interval {
+        self.probability_interval().map_rev(|p| 1.0 / p)
+    }
+
+    /// Interval of [`chance_within`](Self::chance_within).
+    pub fn chance_within_interval(&self, flames: i32) -> Interval {
+        self.probability_interval()
+            .map(|p| chance_within(p, flames))
+    }
+
+    /// Half-width of the probability interval relative to the probability, infinite if no flame
+    /// reached the threshold.
+    pub fn relative_precision(&self) -> f64 {
+        let interval = self.probability_interval();
+        (interval.upper - interval.lower) / 2.0 / self.probability()
+    }
+
+    /// Whether too few flames reached the threshold for the estimates to be meaningful.
+    pub fn is_reliable(&self) -> bool {
+        self.count >= confidence::MIN_RELIABLE_COUNT
+    }
+
+    /// Result for one of the extra thresholds, without top flames or distribution, so the
+    /// same estimates can be read for it.
+    pub fn for_threshold(&self, threshold: &ThresholdCount) -> SimulationResult {
+        SimulationResult {
+            trials: self.trials,
+            count: threshold.count,
+            weights: threshold.weights,
+            top: Vec::new(),
+            distribution: None,
+            thresholds: Vec::new(),
+        }
+    }
+}
+
+fn chance_within(probability: f64, flames: i32) -> f64 {
+    1.0 - (1.0 - probability).powi(flames)
+}
+
+/// Kind of equipment being flamed, which decides the pool of lines it can roll.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ItemType {
+    #[default]
+    Armor,
+    /// Weapon attack lines scale with the weapon's base attack.
+    Weapon { base_att: u16 },
+}
+
+/// Amount of lines a non-boss flame can roll, and the odds of each.
+pub(crate) const NOBOSS_LINES: [usize; 4] = [1, 2, 3, 4];
+pub(crate) const NOBOSS_LINE_WEIGHTS: [f32; 4] = [0.39, 0.39, 0.18, 0.04];
+
+pub struct FlameSimulator {
+    formula: ScoreFormula,
+    flametype: FlameType,
+    pub(crate) noboss: bool,
+    tier_weights: Vec<f32>,
+    pub(crate) option_table: Vec<Item<u16>>,
+    sampler: FlameSampler,
+    tilted: Option<TiltedSampler>,
+    collect_scores: bool,
+    thresholds: Vec<f32>,
+}
+
+/// Tier weights used for each line, which depend on whether the flame is a boss flame.
+fn line_tier_weights(tier_weights: &[f32], noboss: bool) -> &[f32] {
+    if noboss {
+        &tier_weights[2..]
+    } else {
+        &tier_weights[..7]
+    }
+}
+
+impl FlameSimulator {
+    /// Builds a simulator for an equip level, given as a [`LevelBracket`] or an [`EquipLevel`].
+    pub fn new(
+        formula: ScoreFormula,
+        level: impl Into<EquipLevel>,
+        flametype: FlameType,
+        noboss: bool,
+        item: ItemType,
+        tables: &GameTables,
+    ) -> Result<FlameSimulator, Error> {
+        let level = level.into();
+        let tier_weights = tables.tier_weights(flametype).to_vec();
+        let option_table = match item {
+            ItemType::Armor => tables.option_table(level.bracket),
+            ItemType::Weapon { base_att: 0 } => return Err(Error::MissingBaseAttack),
+            ItemType::Weapon { .. } if level.level.is_none() => {
+                return Err(Error::MissingWeaponLevel(level.bracket))
+            }
+            ItemType::Weapon { base_att } => tables.weapon_option_table(level, base_att),
+        };
+        let sampler = FlameSampler::new(
+            &option_table,
+            line_tier_weights(&tier_weights, noboss),
+            noboss.then_some((&NOBOSS_LINES[..], &NOBOSS_LINE_WEIGHTS[..])),
+        );
+
+        Ok(FlameSimulator {
+            formula,
+            flametype,
+            noboss,
+            tier_weights,
+            option_table,
+            sampler,
+            tilted: None,
+            collect_scores: false,
+            thresholds: Vec::new(),
+        })
+    }
+
+    pub fn flametype(&self) -> FlameType {
+        self.flametype
+    }
+
+    /// Tier weights used for each line, which depend on whether the flame is a boss flame.
+    pub(crate) fn line_tier_weights(&self) -> &[f32] {
+        line_tier_weights(&self.tier_weights, self.noboss)
+    }
+
+    /// Biases the rolls of [`run`](Self::run) and [`run_until`](Self::run_until) toward
+    /// flames scoring about `target`, weighting every flame so the odds stay unbiased.
+    ///
+    /// Rare targets are hit far more often this way, so their odds need far fewer trials.
+    /// Every tier is tilted toward higher scores by the same factor per score point, and lines
+    /// are picked more often the higher they can score.
+    pub fn with_importance_sampling(mut self, target: f32) -> FlameSimulator {
+        let scores: Vec<Vec<f64>> = self
+            .option_table
+            .iter()
+            .map(|option| {
+                option
+                    .v
+                    .iter()
+                    .map(|value| {
+                        let mut stats = [0; FLAME_STATS.len()];
+                        add_line(&mut stats, option.n, *value);
+                        self.formula.score_stats(&stats) as f64
+                    })
+                    .collect()
+            })
+            .collect();
+        self.tilted = Some(TiltedSampler::new(
+            &self.option_table,
+            self.line_tier_weights(),
+            self.noboss
+                .then_some((&NOBOSS_LINES[..], &NOBOSS_LINE_WEIGHTS[..])),
+            &scores,
+            target as f64,
+        ));
+        self
+    }
+
+    /// Makes [`run`](Self::run) and [`run_until`](Self::run_until) collect the score
+    /// distribution of every flame, not just the count reaching the threshold.
+    pub fn with_score_distribution(mut self) -> FlameSimulator {
+        self.collect_scores = true;
+        self
+    }
+
+    /// Makes [`run`](Self::run) and [`run_until`](Self::run_until) also count the flames
+    /// reaching each of `thresholds`, from the same rolls as the `keep` threshold.
+    ///
+    /// Adaptive runs still stop on the precision of `keep` alone.
+    pub fn with_thresholds(mut self, thresholds: Vec<f32>) -> FlameSimulator {
+        self.thresholds = thresholds;
+        self
+    }
+
+    /// Whether runs are biased with [`with_importance_sampling`](Self::with_importance_sampling).
+    pub fn is_importance_sampled(&self) -> bool {
+        self.tilted.is_some()
+    }
+
+    /// Rolls a single flame.
+    pub fn roll(&self) -> Flame {
+        self.roll_with(&mut rand::thread_rng())
+    }
+
+    /// Rolls a single flame with the given random number generator.
+    pub fn roll_with<R: Rng + ?Sized>(&self, rng: &mut R) -> Flame {
+        let stats = self.sampler.roll(rng);
+        Flame::from_stats(&stats, self.formula.score_stats(&stats))
+    }
+
+    /// Scores a flame with the configured formula.
+    pub fn score(&self, flame: &Flame) -> f32 {
+        self.formula.score(flame)
+    }
+
+    pub fn formula(&self) -> &ScoreFormula {
+        &self.formula
+    }
+
+    /// Rolls `trials` flames, counting those scoring at least `keep` and collecting the `top` best.
+    ///
+    /// Trials are split into fixed chunks that each roll from their own stream of the `seed`,
+    /// so a seed gives the same result regardless of how many threads run it. `on_progress` is
+    /// called with the amount of flames rolled after every chunk, e.g. to drive a progress bar.
+    pub fn run<F>(
+        &self,
+        trials: u64,
+        keep: f32,
+        top: usize,
+        seed: u64,
+        on_progress: F,
+    ) -> SimulationResult
+    where
+        F: Fn(u64) + Sync,
+    {
+        self.run_chunks(0, trials, keep, top, seed, &on_progress)
+            .into_result(trials, self)
+    }
+
+    /// Rolls flames in batches until the probability is known to the given `precision`, or
+    /// its trial cap is reached.
+    ///
+    /// Batches continue the chunks of the previous one, so the result is the same as a
+    /// [`run`](Self::run) with the final amount of trials and the same `seed`. `on_batch` is
+    /// called with the running totals after every batch.
+    pub fn run_until<F, B>(
+        &self,
+        precision: Precision,
+        keep: f32,
+        top: usize,
+        seed: u64,
+        on_progress: F,
+        mut on_batch: B,
+    ) -> SimulationResult
+    where
+        F: Fn(u64) + Sync,
+        B: FnMut(&SimulationResult),
+    {
+        let mut totals = Accumulator::new(top, self.thresholds.len());
+        let mut trials = 0;
+        let mut relative_precision = f64::INFINITY;
+        loop {
+            let batch = precision.next_batch(relative_precision, trials);
+            let end = (trials + batch).min(precision.max_trials);
+            totals = totals.merge(self.run_chunks(trials, end, keep, top, seed, &on_progress));
+            trials = end;
+
+            let result = totals.clone().into_result(trials, self);
+            relative_precision = result.relative_precision();
+            on_batch(&result);
+            if trials >= precision.max_trials || result.relative_precision() <= precision.relative {
+                return result;
+            }
+        }
+    }
+
+    /// Rolls trials `start..end`, where `start` is a multiple of the chunk size.
+    fn run_chunks<F>(
+        &self,
+        start: u64,
+        end: u64,
+        keep: f32,
+        top: usize,
+        seed: u64,
+        on_progress: &F,
+    ) -> Accumulator
+    where
+        F: Fn(u64) + Sync,
+    {
+        let keeps: Vec<f32> = std::iter::once(keep)
+            .chain(self.thresholds.iter().copied())
+            .collect();
+        // every chunk accumulates on its own and the partial results are merged at the end
+        (start / CHUNK_TRIALS..end.div_ceil(CHUNK_TRIALS))
+            .into_par_iter()
+            .map(|chunk| {
+                let mut rng = ChaCha8Rng::seed_from_u64(seed);
+                rng.set_stream(chunk);
+
+                let mut acc = Accumulator::new(top, self.thresholds.len());
+                let mut weights = vec![Weights::default(); keeps.len()];
+                let trials = chunk * CHUNK_TRIALS..end.min((chunk + 1) * CHUNK_TRIALS);
+                let rolled = trials.end - trials.start;
+                for _ in trials {
+                    let (stats, weight) = match &self.tilted {
+                        Some(tilted) => tilted.roll(&mut rng),
+                        None => (self.sampler.roll(&mut rng), 1.0),
+                    };
+                    let score = self.formula.score_stats(&stats);
+                    for (i, keep) in keeps.iter().enumerate() {
+                        if score >= *keep {
+                            acc.counts[i] += 1;
+                            weights[i].sum += weight;
+                            weights[i].sum_sq += weight * weight;
+                        }
+                    }
+                    if self.collect_scores {
+                        *acc.scores.entry(exact::to_key(score as f64)).or_insert(0) +=
+                            to_fixed(weight);
+                    }
+                    acc.top.push(RolledFlame { score, stats });
+                }
+                acc.chunk_weights.push(weights);
+                on_progress(rolled);
+                acc
+            })
+            .reduce(
+                || Accumulator::new(top, self.thresholds.len()),
+                Accumulator::merge,
+            )
+    }
+}
+
+/// Target of [`FlameSimulator::run_until`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Precision {
+    /// Half-width of the probability's confidence interval, relative to the probability.
+    pub relative: f64,
+    /// Most trials to roll, even if the precision isn't reached.
+    pub max_trials: u64,
+}
+
+impl Precision {
+    /// Trials to roll next, a multiple of the chunk size.
+    ///
+    /// Aims for the amount of trials the precision needs given the `current` precision after
+    /// `trials` trials, but at most doubles the trials so far, since early estimates are rough.
+    fn next_batch(&self, current: f64, trials: u64) -> u64 {
+        let first = MIN_BATCH_CHUNKS * CHUNK_TRIALS;
+        if !current.is_finite() {
+            return first.max(trials);
+        }
+        // the half-width shrinks with the square root of the trials
+        let needed = trials as f64 * (current / self.relative).powi(2);
+        let batch = (needed - trials as f64).clamp(first as f64, trials.max(first) as f64);
+        (batch as u64).div_ceil(CHUNK_TRIALS) * CHUNK_TRIALS
+    }
+}
+
+/// Trials rolled from a single random number stream.
+const CHUNK_TRIALS: u64 = 1 << 14;
+
+/// Chunks in the smallest batch of an adaptive run.
+const MIN_BATCH_CHUNKS: u64 = 16;
+
+/// One in fixed-point weights, which add up to the same totals in any order.
+const FIXED_ONE: f64 = (1u128 << 64) as f64;
+
+fn to_fixed(weight: f64) -> u128 {
+    (weight * FIXED_ONE) as u128
+}
+
+/// Per-task totals of a simulation run.
+///
+/// Counts and weights hold the `keep` threshold first, then every extra threshold.
+#[derive(Clone)]
+struct Accumulator {
+    counts: Vec<u64>,
+    /// Weights of every chunk, in chunk order so they always add up to the same sums.
+    chunk_weights: Vec<Vec<Weights>>,
+    /// Fixed-point weight of every score key, if scores are collected.
+    scores: HashMap<i64, u128>,
+    top: TopFlames,
+}
+
+impl Accumulator {
+    fn new(top: usize, thresholds: usize) -> Accumulator {
+        Accumulator {
+            counts: vec![0; thresholds + 1],
+            chunk_weights: Vec::new(),
+            scores: HashMap::new(),
+            top: TopFlames::new(top),
+        }
+    }
+
+    fn into_result(self, trials: u64, simulator: &FlameSimulator) -> SimulationResult {
+        let weighted = simulator.is_importance_sampled();
+        let weights = |i: usize| {
+            let weights = self
+                .chunk_weights
+                .iter()
+                .fold(Weights::default(), |total, chunk| Weights {
+                    sum: total.sum + chunk[i].sum,
+                    sum_sq: total.sum_sq + chunk[i].sum_sq,
+                });
+            weighted.then_some(weights)
+        };
+        SimulationResult {
+            trials,
+            count: self.counts[0],
+            weights: weights(0),
+            thresholds: (simulator.thresholds.iter().zip(1..))
+                .map(|(keep, i)| ThresholdCount {
+                    keep: *keep,
+                    count: self.counts[i],
+                    weights: weights(i),
+                })
+                .collect(),
+            top: self.top.into_sorted(),
+            distribution: simulator.collect_scores.then(|| {
+                ScoreDistribution::from_weights(
+                    self.scores
+                        .into_iter()
+                        .map(|(key, weight)| (key, weight as f64 / FIXED_ONE)),
+                )
+            }),
+        }
+    }
+
+    /// Merges totals of later chunks into these.
+    fn merge(mut self, other: Accumulator) -> Accumulator {
+        for (count, other) in self.counts.iter_mut().zip(other.counts) {
+            *count += other;
+        }
+        self.chunk_weights.extend(other.chunk_weights);
+        for (key, weight) in other.scores {
+            *self.scores.entry(key).or_insert(0) += weight;
+        }
+        for flame in other.top.heap {
+            self.top.push(flame.0);
+        }
+        self
+    }
+}
+
+/// A scored roll, ordered by score, then by its stats so ties keep the same flames on every run.
+#[derive(Clone, Copy)]
+struct RolledFlame {
+    score: f32,
+    stats: Stats,
+}
+
+impl PartialEq for RolledFlame {
+    fn eq(&self, other: &RolledFlame) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RolledFlame {}
+
+impl PartialOrd for RolledFlame {
+    fn partial_cmp(&self, other: &RolledFlame) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for RolledFlame {
+    fn cmp(&self, other: &RolledFlame) -> Ordering {
+        self.score
+            .total_cmp(&other.score)
+            .then_with(|| self.stats.cmp(&other.stats))
+    }
+}
+
+/// Keeps the `capacity` highest scoring flames pushed into it.
+#[derive(Clone)]
+struct TopFlames {
+    capacity: usize,
+    // min-heap, so the lowest kept flame is the one replaced
+    heap: BinaryHeap<Reverse<RolledFlame>>,
+}
+
+impl TopFlames {
+    fn new(capacity: usize) -> TopFlames {
+        TopFlames {
+            capacity,
+            heap: BinaryHeap::with_capacity(capacity + 1),
+        }
+    }
+
+    fn push(&mut self, flame: RolledFlame) {
+        if self.heap.len() < self.capacity {
+            self.heap.push(Reverse(flame));
+        } else if let Some(mut lowest) = self.heap.peek_mut() {
+            if flame > lowest.0 {
+                *lowest = Reverse(flame);
+            }
+        }
+    }
+
+    /// Returns the kept flames, highest score first.
+    fn into_sorted(self) -> Vec<Flame> {
+        // sorting `Reverse` ascending puts the highest score first
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|flame| Flame::from_stats(&flame.0.stats, flame.0.score))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, FlameSimulator, ItemType};
+    use crate::score::{ScoreFormula, ScoreWeights};
+    use crate::tables::GameTables;
+    use crate::types::{EquipLevel, FlameType, JobStat, LevelBracket};
+
+    fn weapon(level: impl Into<EquipLevel>) -> Result<FlameSimulator, Error> {
+        FlameSimulator::new(
+            ScoreFormula::preset(JobStat::Str, &ScoreWeights::default()),
+            level,
+            FlameType::Eflame,
+            false,
+            ItemType::Weapon { base_att: 326 },
+            &GameTables::bundled(),
+        )
+    }
+
+    #[test]
+    fn weapons_need_an_equip_level() {
+        assert!(matches!(
+            weapon(LevelBracket::L200),
+            Err(Error::MissingWeaponLevel(LevelBracket::L200))
+        ));
+        assert!(weapon(EquipLevel::from_level(240).unwrap()).is_ok());
+    }
+}
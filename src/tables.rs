@@ -0,0 +1,519 @@
+//! Game tables: tier weights and prices per flame type, and line values per equip level.
+//!
+//! The tables are loaded from a versioned TOML file. A copy of `flame_tables.toml` is bundled
+//! into the binary so the simulator works without it, and a patched file can be loaded instead.
+
+use crate::types::{EquipLevel, FlameType, LevelBracket};
+use clap::ValueEnum;
+use serde::Deserialize;
+use std::{collections::HashMap, fmt, fs, io};
+
+/// Contents of the bundled `flame_tables.toml`.
+pub const BUNDLED_TABLES: &str = include_str!("../flame_tables.toml");
+
+/// Table file version this build understands.
+pub const TABLES_VERSION: u32 = 1;
+
+/// Amount of tier weights listed per flame type.
+const TIER_WEIGHT_COUNT: usize = 9;
+
+/// Amount of tiers a line can roll.
+const TIER_COUNT: usize = 7;
+
+#[derive(Clone, Debug)]
+pub struct Item<T> {
+    pub n: &'static str,
+    pub v: Vec<T>,
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    Parse(toml::de::Error),
+    UnsupportedVersion(u32),
+    MissingFlameType(FlameType),
+    MissingBracket(LevelBracket),
+    /// A table doesn't list the expected amount of values.
+    WrongLength {
+        table: String,
+        expected: usize,
+        found: usize,
+    },
+    /// A flame type's tier weights are negative or can't roll any tier.
+    InvalidWeights(FlameType),
+    /// A table lists a flame type or level bracket that doesn't exist.
+    UnknownKey {
+        table: String,
+        key: String,
+    },
+    /// A flame type's price is negative or not a number.
+    InvalidPrice(FlameType),
+    /// The meso to NX exchange rate isn't above 0.
+    InvalidExchangeRate(f64),
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::Io(e) => write!(f, "{}", e),
+            TableError::Parse(e) => write!(f, "{}", e),
+            TableError::UnsupportedVersion(version) => write!(
+                f,
+                "unsupported table version {} (expected {})",
+                version, TABLES_VERSION
+            ),
+            TableError::MissingFlameType(flametype) => {
+                write!(f, "missing tier weights for flame type {}", flametype)
+            }
+            TableError::MissingBracket(level) => {
+                write!(f, "missing line values for level bracket {}", level)
+            }
+            TableError::WrongLength {
+                table,
+                expected,
+                found,
+            } => write!(f, "{} has {} values, expected {}", table, found, expected),
+            TableError::InvalidWeights(flametype) => write!(
+                f,
+                "tier weights for {} must be non-negative and roll at least one tier",
+                flametype
+            ),
+            TableError::UnknownKey { table, key } => {
+                write!(f, "{} lists unknown key \"{}\"", table, key)
+            }
+            TableError::InvalidPrice(flametype) => {
+                write!(f, "price of {} must be a non-negative number", flametype)
+            }
+            TableError::InvalidExchangeRate(rate) => {
+                write!(f, "meso_per_nx must be above 0, found {}", rate)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<io::Error> for TableError {
+    fn from(e: io::Error) -> TableError {
+        TableError::Io(e)
+    }
+}
+
+impl From<toml::de::Error> for TableError {
+    fn from(e: toml::de::Error) -> TableError {
+        TableError::Parse(e)
+    }
+}
+
+/// Layout of the table file, before validation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TableFile {
+    version: u32,
+    tier_weights: HashMap<String, Vec<f32>>,
+    weapon: WeaponLines,
+    levels: HashMap<String, LevelLines>,
+    /// Files without prices use the bundled ones.
+    costs: Option<CostLines>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct CostLines {
+    meso_per_nx: f64,
+    #[serde(default)]
+    prices: HashMap<String, Price>,
+}
+
+/// Price of a single flame.
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Price {
+    /// Meso, for flames bought with meso or crafted from materials worth that much.
+    Meso(f64),
+    /// NX, converted to meso with the exchange rate.
+    Nx(f64),
+}
+
+/// Price of a single flame and what it's worth in meso.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FlameCost {
+    pub price: Price,
+    pub meso: f64,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct WeaponLines {
+    boss: Vec<u16>,
+    dmg: Vec<u16>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+struct LevelLines {
+    flat: Vec<u16>,
+    combo: Vec<u16>,
+    hpmp: Vec<u16>,
+    basic: Vec<u16>,
+}
+
+fn check_length<T>(table: String, values: &[T], expected: usize) -> Result<(), TableError> {
+    if values.len() != expected {
+        return Err(TableError::WrongLength {
+            table,
+            expected,
+            found: values.len(),
+        });
+    }
+    Ok(())
+}
+
+/// Rejects keys of a table that aren't the name of a `T`, so typos don't go unnoticed.
+fn check_keys<T: ValueEnum, V>(table: &str, values: &HashMap<String, V>) -> Result<(), TableError> {
+    match values.keys().find(|key| T::from_str(key, false).is_err()) {
+        Some(key) => Err(TableError::UnknownKey {
+            table: table.to_owned(),
+            key: key.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Validated game tables, with an entry for every flame type and level bracket.
+#[derive(Clone, Debug)]
+pub struct GameTables {
+    /// Indexed by `FlameType as usize`.
+    tier_weights: Vec<Vec<f32>>,
+    weapon: WeaponLines,
+    /// Indexed by `LevelBracket as usize`.
+    levels: Vec<LevelLines>,
+    /// Indexed by `FlameType as usize`, `None` for flame types that can't be bought.
+    prices: Vec<Option<Price>>,
+    meso_per_nx: f64,
+}
+
+impl GameTables {
+    /// Tables bundled with this build.
+    pub fn bundled() -> GameTables {
+        GameTables::parse(BUNDLED_TABLES).expect("bundled flame_tables.toml is valid")
+    }
+
+    /// Reads and validates a table file.
+    pub fn load(path: &str) -> Result<GameTables, TableError> {
+        GameTables::parse(&fs::read_to_string(path)?)
+    }
+
+    /// Parses and validates table file contents.
+    pub fn parse(contents: &str) -> Result<GameTables, TableError> {
+        GameTables::validate(toml::from_str(contents)?)
+    }
+
+    fn validate(file: TableFile) -> Result<GameTables, TableError> {
+        if file.version != TABLES_VERSION {
+            return Err(TableError::UnsupportedVersion(file.version));
+        }
+
+        check_keys::<FlameType, _>("tier_weights", &file.tier_weights)?;
+        let mut tier_weights = Vec::new();
+        for flametype in FlameType::value_variants() {
+            let weights = file
+                .tier_weights
+                .get(&flametype.to_string())
+                .ok_or(TableError::MissingFlameType(*flametype))?;
+            check_length(
+                format!("tier_weights.{}", flametype),
+                weights,
+                TIER_WEIGHT_COUNT,
+            )?;
+            let rolls_boss = weights[..TIER_COUNT].iter().any(|w| *w > 0.0);
+            let rolls_noboss = weights[TIER_WEIGHT_COUNT - TIER_COUNT..]
+                .iter()
+                .any(|w| *w > 0.0);
+            if weights.iter().any(|w| *w < 0.0) || !rolls_boss || !rolls_noboss {
+                return Err(TableError::InvalidWeights(*flametype));
+            }
+            tier_weights.push(weights.clone());
+        }
+
+        check_length("weapon.boss".to_owned(), &file.weapon.boss, TIER_COUNT)?;
+        check_length("weapon.dmg".to_owned(), &file.weapon.dmg, TIER_COUNT)?;
+
+        check_keys::<LevelBracket, _>("levels", &file.levels)?;
+        let mut levels = Vec::new();
+        for level in LevelBracket::value_variants() {
+            let lines = file
+                .levels
+                .get(&level.to_string())
+                .ok_or(TableError::MissingBracket(*level))?;
+            for (name, values) in [
+                ("flat", &lines.flat),
+                ("combo", &lines.combo),
+                ("hpmp", &lines.hpmp),
+                ("basic", &lines.basic),
+            ] {
+                check_length(format!("levels.\"{}\".{}", level, name), values, TIER_COUNT)?;
+            }
+            levels.push(lines.clone());
+        }
+
+        let costs = match file.costs {
+            Some(costs) => costs,
+            None => toml::from_str::<TableFile>(BUNDLED_TABLES)?
+                .costs
+                .expect("bundled flame_tables.toml has prices"),
+        };
+        if !(costs.meso_per_nx > 0.0 && costs.meso_per_nx.is_finite()) {
+            return Err(TableError::InvalidExchangeRate(costs.meso_per_nx));
+        }
+        check_keys::<FlameType, _>("costs.prices", &costs.prices)?;
+        let mut prices = Vec::new();
+        for flametype in FlameType::value_variants() {
+            let price = costs.prices.get(&flametype.to_string()).copied();
+            if let Some(Price::Meso(amount) | Price::Nx(amount)) = price {
+                if !(amount >= 0.0 && amount.is_finite()) {
+                    return Err(TableError::InvalidPrice(*flametype));
+                }
+            }
+            prices.push(price);
+        }
+
+        Ok(GameTables {
+            tier_weights,
+            weapon: file.weapon,
+            levels,
+            prices,
+            meso_per_nx: costs.meso_per_nx,
+        })
+    }
+
+    /// Converts NX prices with `meso_per_nx` meso per NX instead of the table's rate.
+    pub fn with_meso_per_nx(mut self, meso_per_nx: f64) -> Result<GameTables, TableError> {
+        if !(meso_per_nx > 0.0 && meso_per_nx.is_finite()) {
+            return Err(TableError::InvalidExchangeRate(meso_per_nx));
+        }
+        self.meso_per_nx = meso_per_nx;
+        Ok(self)
+    }
+
+    /// Meso exchanged for one NX.
+    pub fn meso_per_nx(&self) -> f64 {
+        self.meso_per_nx
+    }
+
+    /// Price of one flame of a type, or `None` if it can't be bought.
+    pub fn price(&self, flametype: FlameType) -> Option<Price> {
+        self.prices[flametype as usize]
+    }
+
+    /// Price of one flame of a type with its meso value, or `None` if it can't be bought.
+    pub fn flame_cost(&self, flametype: FlameType) -> Option<FlameCost> {
+        let price = self.price(flametype)?;
+        Some(FlameCost {
+            price,
+            meso: match price {
+                Price::Meso(meso) => meso,
+                Price::Nx(nx) => nx * self.meso_per_nx,
+            },
+        })
+    }
+
+    /// Returns the tier weights for the given flame type.
+    pub fn tier_weights(&self, flametype: FlameType) -> &[f32] {
+        &self.tier_weights[flametype as usize]
+    }
+
+    /// Builds the table of rollable lines for an equip level.
+    ///
+    /// Each entry holds the line name and its value for every tier.
+    pub fn option_table(&self, level: LevelBracket) -> Vec<Item<u16>> {
+        let lines = &self.levels[level as usize];
+        let flat = lines.flat.clone();
+        let combo = lines.combo.clone();
+        let hpmp = lines.hpmp.clone();
+        let basic = lines.basic.clone();
+        vec![
+            Item {
+                n: "str",
+                v: flat.clone(),
+            },
+            Item {
+                n: "dex",
+                v: flat.clone(),
+            },
+            Item {
+                n: "int",
+                v: flat.clone(),
+            },
+            Item { n: "luk", v: flat },
+            Item {
+                n: "strdex",
+                v: combo.clone(),
+            },
+            Item {
+                n: "strint",
+                v: combo.clone(),
+            },
+            Item {
+                n: "strluk",
+                v: combo.clone(),
+            },
+            Item {
+                n: "dexint",
+                v: combo.clone(),
+            },
+            Item {
+                n: "dexluk",
+                v: combo.clone(),
+            },
+            Item {
+                n: "lukint",
+                v: combo,
+            },
+            Item {
+                n: "hp",
+                v: hpmp.clone(),
+            },
+            Item { n: "mp", v: hpmp },
+            Item {
+                n: "def",
+                v: basic.clone(),
+            },
+            Item {
+                n: "lvlred",
+                v: basic.clone(),
+            },
+            Item {
+                n: "att",
+                v: basic.clone(),
+            },
+            Item {
+                n: "matt",
+                v: basic.clone(),
+            },
+            Item {
+                n: "speed",
+                v: basic.clone(),
+            },
+            Item {
+                n: "jmp",
+                v: basic.clone(),
+            },
+            Item { n: "as", v: basic },
+        ]
+    }
+
+    /// Builds the table of rollable weapon lines for an equip level and base weapon attack.
+    ///
+    /// Weapons can't roll speed or jump, and roll attack as a percentage of base attack plus
+    /// boss damage %, damage % and all stat %.
+    ///
+    /// Weapon attack scales with the exact equip level, the other lines with its bracket.
+    pub fn weapon_option_table(&self, level: EquipLevel, base_att: u16) -> Vec<Item<u16>> {
+        let weapon_att: Vec<u16> = weapon_att_percents(level.level())
+            .iter()
+            .map(|percent| (base_att as f32 * percent / 100.0).ceil() as u16)
+            .collect();
+        let boss = self.weapon.boss.clone();
+        let dmg = self.weapon.dmg.clone();
+
+        let mut table: Vec<Item<u16>> = self
+            .option_table(level.bracket)
+            .into_iter()
+            .filter(|a| !matches!(a.n, "speed" | "jmp"))
+            .map(|a| match a.n {
+                "att" | "matt" => Item {
+                    n: a.n,
+                    v: weapon_att.clone(),
+                },
+                _ => a,
+            })
+            .collect();
+        table.push(Item { n: "boss", v: boss });
+        table.push(Item { n: "dmg", v: dmg });
+        table
+    }
+}
+
+/// Weapon attack gained per tier, as a percentage of the weapon's base attack.
+///
+/// Each tier grants `tier * 1.1^(tier - 1)` percent, scaled by one step per 40 equip levels.
+fn weapon_att_percents(level: u16) -> Vec<f32> {
+    let multiplier = (level / 40 + 1) as f32;
+    (1..=7)
+        .map(|tier| multiplier * tier as f32 * 1.1_f32.powi(tier - 1))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The bundled tables with `from` replaced by `to`.
+    fn patched(from: &str, to: &str) -> Result<GameTables, TableError> {
+        assert!(BUNDLED_TABLES.contains(from), "no '{}' to patch", from);
+        GameTables::parse(&BUNDLED_TABLES.replacen(from, to, 1))
+    }
+
+    fn unknown_key(result: Result<GameTables, TableError>) -> Option<(String, String)> {
+        match result {
+            Err(TableError::UnknownKey { table, key }) => Some((table, key)),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        assert_eq!(
+            unknown_key(patched("[levels.\"200-249\"]", "[levels.\"200-250\"]")),
+            Some(("levels".to_owned(), "200-250".to_owned()))
+        );
+        assert_eq!(
+            unknown_key(patched("eflame = [", "eflmae = [")),
+            Some(("tier_weights".to_owned(), "eflmae".to_owned()))
+        );
+        assert_eq!(
+            unknown_key(patched("pflame = { meso", "pflam = { meso")),
+            Some(("costs.prices".to_owned(), "pflam".to_owned()))
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_fields() {
+        for (from, to) in [
+            ("version = 1", "version = 1\nextra = 2"),
+            ("[weapon]", "[weapon]\natt = [1]"),
+            (
+                "meso_per_nx = 1000.0",
+                "meso_per_nx = 1000.0\nmeso_per_mp = 1.0",
+            ),
+        ] {
+            assert!(
+                matches!(patched(from, to), Err(TableError::Parse(_))),
+                "'{}' was accepted",
+                to
+            );
+        }
+        let level = BUNDLED_TABLES.find("[levels.").unwrap();
+        let mut contents = BUNDLED_TABLES.to_owned();
+        contents.insert_str(
+            level + BUNDLED_TABLES[level..].find('\n').unwrap() + 1,
+            "fat = [1]\n",
+        );
+        assert!(matches!(
+            GameTables::parse(&contents),
+            Err(TableError::Parse(_))
+        ));
+    }
+
+    #[test]
+    fn reports_missing_entries() {
+        assert!(matches!(
+            patched(
+                "abyss = [0.0, 0.0, 0.0, 0.0, 0.63, 0.34, 0.03, 0.0, 0.0]\n",
+                ""
+            ),
+            Err(TableError::MissingFlameType(FlameType::Abyss))
+        ));
+    }
+}
@@ -0,0 +1,238 @@
+//! Typed simulator settings, parsed from the command line with clap.
+
+use clap::builder::{EnumValueParser, PossibleValue, TypedValueParser};
+use clap::error::ErrorKind;
+use clap::{Arg, Command, ValueEnum};
+use std::{ffi::OsStr, fmt};
+
+/// Stat (or class) a flame is scored for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum JobStat {
+    Str,
+    Dex,
+    Int,
+    Luk,
+    Kanna,
+    Da,
+    Xenon,
+    #[value(name = "alt_thief")]
+    AltThief,
+}
+
+/// Source of the flame, which decides the tier weights.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FlameType {
+    Abyss,
+    Totem,
+    Drop,
+    Pflame,
+    Eflame,
+    Regcraft,
+    Mastercraft,
+    Meistercraft,
+    Masterfuse,
+    Meisterfuse,
+}
+
+/// How results are printed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    /// Human-readable text.
+    Text,
+    /// A single JSON document.
+    Json,
+    /// A CSV table with one row per top flame.
+    Csv,
+    /// One JSON object per line.
+    Ndjson,
+}
+
+/// Equip level bracket, which decides the value of each line tier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum LevelBracket {
+    #[value(name = "100-109")]
+    L100,
+    #[value(name = "110-119")]
+    L110,
+    #[value(name = "120-129")]
+    L120,
+    #[value(name = "130-139")]
+    L130,
+    #[value(name = "140-149")]
+    L140,
+    #[value(name = "150-159")]
+    L150,
+    #[value(name = "160-169")]
+    L160,
+    #[value(name = "170-179")]
+    L170,
+    #[value(name = "180-189")]
+    L180,
+    #[value(name = "190-199")]
+    L190,
+    #[value(name = "200-249")]
+    L200,
+    #[value(name = "250+")]
+    L250,
+}
+
+impl LevelBracket {
+    /// Highest equip level accepted when giving a level instead of a bracket.
+    pub const MAX_LEVEL: u16 = 300;
+
+    /// Returns the bracket an equip level falls in, or `None` if flames aren't simulated for it.
+    pub fn from_level(level: u16) -> Option<LevelBracket> {
+        match level {
+            100..=109 => Some(LevelBracket::L100),
+            110..=119 => Some(LevelBracket::L110),
+            120..=129 => Some(LevelBracket::L120),
+            130..=139 => Some(LevelBracket::L130),
+            140..=149 => Some(LevelBracket::L140),
+            150..=159 => Some(LevelBracket::L150),
+            160..=169 => Some(LevelBracket::L160),
+            170..=179 => Some(LevelBracket::L170),
+            180..=189 => Some(LevelBracket::L180),
+            190..=199 => Some(LevelBracket::L190),
+            200..=249 => Some(LevelBracket::L200),
+            250..=LevelBracket::MAX_LEVEL => Some(LevelBracket::L250),
+            _ => None,
+        }
+    }
+
+    /// Lowest equip level in the bracket.
+    pub fn min_level(self) -> u16 {
+        match self {
+            LevelBracket::L100 => 100,
+            LevelBracket::L110 => 110,
+            LevelBracket::L120 => 120,
+            LevelBracket::L130 => 130,
+            LevelBracket::L140 => 140,
+            LevelBracket::L150 => 150,
+            LevelBracket::L160 => 160,
+            LevelBracket::L170 => 170,
+            LevelBracket::L180 => 180,
+            LevelBracket::L190 => 190,
+            LevelBracket::L200 => 200,
+            LevelBracket::L250 => 250,
+        }
+    }
+}
+
+/// Level of the flamed equip: its bracket, and the exact level if one was given.
+///
+/// Line values only depend on the bracket, but weapon attack scales with the exact level.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EquipLevel {
+    pub bracket: LevelBracket,
+    /// `None` when only the bracket was given.
+    pub level: Option<u16>,
+}
+
+impl EquipLevel {
+    /// Returns the equip level, or `None` if flames aren't simulated for it.
+    pub fn from_level(level: u16) -> Option<EquipLevel> {
+        Some(EquipLevel {
+            bracket: LevelBracket::from_level(level)?,
+            level: Some(level),
+        })
+    }
+
+    /// The exact level if one was given, otherwise the lowest level of the bracket.
+    pub fn level(self) -> u16 {
+        self.level.unwrap_or(self.bracket.min_level())
+    }
+}
+
+impl From<LevelBracket> for EquipLevel {
+    fn from(bracket: LevelBracket) -> EquipLevel {
+        EquipLevel {
+            bracket,
+            level: None,
+        }
+    }
+}
+
+/// Shows the bracket, preceded by the exact level if one was given, e.g. `240 (200-249)`.
+impl fmt::Display for EquipLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.level {
+            Some(level) => write!(f, "{} ({})", level, self.bracket),
+            None => write!(f, "{}", self.bracket),
+        }
+    }
+}
+
+/// Parses either a bracket name (e.g. `200-249`) or an equip level (e.g. `240`).
+#[derive(Clone)]
+pub struct EquipLevelParser;
+
+impl TypedValueParser for EquipLevelParser {
+    type Value = EquipLevel;
+
+    fn parse_ref(
+        &self,
+        cmd: &Command,
+        arg: Option<&Arg>,
+        value: &OsStr,
+    ) -> Result<EquipLevel, clap::Error> {
+        let brackets = EnumValueParser::<LevelBracket>::new();
+        if let Ok(bracket) = brackets.parse_ref(cmd, arg, value) {
+            return Ok(bracket.into());
+        }
+
+        let value = value.to_string_lossy();
+        let level = value.parse::<u16>().ok();
+        match level.and_then(EquipLevel::from_level) {
+            Some(level) => Ok(level),
+            None => {
+                let brackets: Vec<String> = self
+                    .possible_values()
+                    .into_iter()
+                    .flatten()
+                    .map(|bracket| bracket.get_name().to_owned())
+                    .collect();
+                let arg = arg.map_or_else(|| "...".to_owned(), |arg| arg.to_string());
+                Err(cmd.clone().error(
+                    ErrorKind::InvalidValue,
+                    format!(
+                        "invalid value '{}' for '{}'\n  [expected an equip level from 100 to {} or one of: {}]",
+                        value,
+                        arg,
+                        LevelBracket::MAX_LEVEL,
+                        brackets.join(", ")
+                    ),
+                ))
+            }
+        }
+    }
+
+    fn possible_values(&self) -> Option<Box<dyn Iterator<Item = PossibleValue> + '_>> {
+        Some(Box::new(
+            LevelBracket::value_variants()
+                .iter()
+                .filter_map(|bracket| bracket.to_possible_value()),
+        ))
+    }
+}
+
+/// Implements `Display` using the name clap parses the value from.
+macro_rules! display_value_name {
+    ($($t:ty),*) => {
+        $(
+            impl fmt::Display for $t {
+                fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                    let value = self.to_possible_value().ok_or(fmt::Error)?;
+                    f.write_str(value.get_name())
+                }
+            }
+        )*
+    };
+}
+
+display_value_name!(
+    JobStat,
+    FlameType,
+    LevelBracket,
+    OutputFormat,
+    crate::jobs::Job
+);